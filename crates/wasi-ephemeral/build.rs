@@ -1,7 +1,6 @@
 use std::{
     env,
     fs::File,
-    io::Write,
     path::{Path, PathBuf},
 };
 
@@ -17,12 +16,14 @@ fn main() {
     let witx_paths: Vec<_> = WITX_MODULES
         .iter()
         .map(|x| {
-            root.join(&["wasi_ephemeral_", x, ".witx"].join(""))
+            root.join(["wasi_ephemeral_", x, ".witx"].join(""))
                 .to_owned()
         })
         .collect();
-    let out = witx_bindgen::generate(&witx_paths);
-    write!(f, "{}", out).unwrap();
+    witx_bindgen::GenerateConfig::new()
+        .multi_module(true)
+        .generate_to(&witx_paths, &mut f)
+        .unwrap_or_else(|e| panic!("failed to generate bindings: {}", e));
     for p in &witx_paths {
         println!("cargo:rerun-if-changed={}", p.display());
     }
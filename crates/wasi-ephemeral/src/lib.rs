@@ -27,5 +27,11 @@
 //! compatible.
 
 #![no_std]
+// The generated bindings are raw imports and don't follow every lint.
+#![allow(
+    clashing_extern_declarations,
+    clippy::missing_safety_doc,
+    clippy::too_many_arguments
+)]
 
 include!(concat!(env!("OUT_DIR"), "/lib_generated.rs"));
@@ -1,104 +1,678 @@
 use heck::*;
-use std::io::{Read, Write};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
 use std::mem;
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::rc::Rc;
 use witx::*;
 
-pub fn generate<P: AsRef<Path>>(witx_paths: &[P]) -> String {
-    let doc = witx::load(witx_paths).unwrap();
-
-    let mut raw = String::new();
-    raw.push_str(
-        "\
+const HEADER: &str = "\
 // This file is automatically generated, DO NOT EDIT
 //
 // To regenerate this file run the `crates/witx-bindgen` command
 
-use core::mem::MaybeUninit;
-use core::fmt;
-",
-    );
-    for ty in doc.typenames() {
-        ty.render(&mut raw);
-        raw.push_str("\n");
-    }
-    for m in doc.modules() {
-        m.render(&mut raw);
-        raw.push_str("\n");
-    }
-    for c in doc.constants() {
-        rustdoc(&c.docs, &mut raw);
-        raw.push_str(&format!(
-            "pub const {}_{}: {} = {};\n",
-            c.ty.as_str().to_shouty_snake_case(),
-            c.name.as_str().to_shouty_snake_case(),
-            c.ty.as_str().to_camel_case(),
-            c.value
-        ));
+";
+
+/// Name of the module, relative to each per-document module, which
+/// `generate_files` places all shared type definitions in.
+const TYPES_MODULE: &str = "types";
+
+/// Version of the `witx` crate, and so of its AST and ABI instruction set,
+/// which this generator is written against.
+pub const WITX_VERSION: &str = "0.9";
+
+/// Errors which can occur while generating bindings.
+#[derive(Debug)]
+pub enum GenerateError {
+    /// A witx document couldn't be read, parsed or validated.
+    Witx(WitxError),
+    /// Formatting the bindings or writing them out failed.
+    Io(io::Error),
+    /// A type or function can't be generated, or a function can't be
+    /// generated yet and lenient mode is off. The message names the offending
+    /// declaration and what about it isn't supported.
+    Unsupported(String),
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // `report` includes the offending path and, for parse and
+            // validation errors, the location within the document.
+            GenerateError::Witx(e) => f.write_str(&e.report()),
+            GenerateError::Io(e) => write!(f, "{}", e),
+            GenerateError::Unsupported(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl Error for GenerateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GenerateError::Witx(e) => Some(e),
+            GenerateError::Io(e) => Some(e),
+            GenerateError::Unsupported(_) => None,
+        }
     }
+}
 
-    let mut rustfmt = Command::new("rustfmt")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    rustfmt
-        .stdin
-        .take()
-        .unwrap()
-        .write_all(raw.as_bytes())
-        .unwrap();
-    let mut ret = String::new();
-    rustfmt
-        .stdout
-        .take()
-        .unwrap()
-        .read_to_string(&mut ret)
-        .unwrap();
-    let status = rustfmt.wait().unwrap();
-    assert!(status.success());
-    return ret;
+impl From<WitxError> for GenerateError {
+    fn from(e: WitxError) -> GenerateError {
+        GenerateError::Witx(e)
+    }
+}
+
+impl From<io::Error> for GenerateError {
+    fn from(e: io::Error) -> GenerateError {
+        GenerateError::Io(e)
+    }
+}
+
+/// Options controlling the shape of the generated bindings.
+///
+/// Every option is off by default, so `GenerateConfig::new()` generates the
+/// same bindings as the free functions such as `generate`.
+#[derive(Debug, Clone, Default)]
+pub struct GenerateConfig {
+    multi_module: bool,
+    module_namespaces: bool,
+    syscall_traits: bool,
+    hex_constants: bool,
+    lenient: bool,
+    modules: Filter,
+    functions: Filter,
+    newtype_aliases: bool,
+    newtype_handles: bool,
+    sorted: bool,
+    header: Option<String>,
+}
+
+impl GenerateConfig {
+    pub fn new() -> GenerateConfig {
+        GenerateConfig::default()
+    }
+
+    /// Prefixes each wrapper function with the name of its module, for
+    /// documents such as wasi-ephemeral whose modules reuse function names.
+    pub fn multi_module(mut self, enable: bool) -> Self {
+        self.multi_module = enable;
+        self
+    }
+
+    /// Renders each module's wrapper functions inside a Rust module named
+    /// after it, e.g. `wasi_ephemeral_fd::read`, with the raw imports moved
+    /// into a nested `raw` module.
+    ///
+    /// This is an alternative to `multi_module` for keeping the wrappers of
+    /// different modules apart, and takes precedence over it.
+    pub fn module_namespaces(mut self, enable: bool) -> Self {
+        self.module_namespaces = enable;
+        self
+    }
+
+    /// Also renders a trait for each module, e.g. `WasiSnapshotPreview1Syscalls`,
+    /// with a method per function that calls its wrapper by default.
+    ///
+    /// Code written against the trait can then be tested against a mock
+    /// implementation rather than the real imports.
+    pub fn syscall_traits(mut self, enable: bool) -> Self {
+        self.syscall_traits = enable;
+        self
+    }
+
+    /// Renders the values of integer constants in hexadecimal, which reads
+    /// better for masks.
+    pub fn hex_constants(mut self, enable: bool) -> Self {
+        self.hex_constants = enable;
+        self
+    }
+
+    /// Skips the wrappers of functions which can't be generated yet, leaving
+    /// a comment in their place, instead of failing with
+    /// `GenerateError::Unsupported`.
+    ///
+    /// The raw imports of skipped functions are still generated, and the
+    /// skipped functions are listed at the end of the bindings.
+    pub fn lenient(mut self, enable: bool) -> Self {
+        self.lenient = enable;
+        self
+    }
+
+    /// Only generates functions for the module called `name`, along with any
+    /// other allowed modules.
+    ///
+    /// All modules are generated if none are allowed explicitly. Types and
+    /// constants are always generated, whichever modules are selected.
+    pub fn allow_module(mut self, name: impl Into<String>) -> Self {
+        self.modules.allow.insert(name.into());
+        self
+    }
+
+    /// Skips the functions of the module called `name`.
+    pub fn deny_module(mut self, name: impl Into<String>) -> Self {
+        self.modules.deny.insert(name.into());
+        self
+    }
+
+    /// Only generates the function called `name`, along with any other
+    /// allowed functions.
+    ///
+    /// Functions are matched by their witx name, e.g. `fd_read`, in every
+    /// module. All functions are generated if none are allowed explicitly.
+    pub fn allow_function(mut self, name: impl Into<String>) -> Self {
+        self.functions.allow.insert(name.into());
+        self
+    }
+
+    /// Skips the function called `name`.
+    pub fn deny_function(mut self, name: impl Into<String>) -> Self {
+        self.functions.deny.insert(name.into());
+        self
+    }
+
+    /// Renders aliases of builtin types as distinct newtypes instead of
+    /// transparent `type` aliases.
+    pub fn newtype_aliases(mut self, enable: bool) -> Self {
+        self.newtype_aliases = enable;
+        self
+    }
+
+    /// Renders handles as distinct newtypes instead of `u32` aliases.
+    pub fn newtype_handles(mut self, enable: bool) -> Self {
+        self.newtype_handles = enable;
+        self
+    }
+
+    /// Sorts types, modules, functions and constants by name, rather than
+    /// keeping the order they're declared in within the witx documents.
+    ///
+    /// This keeps regenerated bindings stable when declarations are only
+    /// moved around, for example between layered documents.
+    pub fn sorted(mut self, enable: bool) -> Self {
+        self.sorted = enable;
+        self
+    }
+
+    /// Inserts `header`, such as a license banner or crate attributes, into
+    /// every generated file right after the notice that it's generated.
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.header = Some(header.into());
+        self
+    }
+
+    /// Generates the bindings for `witx_paths` as a single source file.
+    pub fn generate<P: AsRef<Path>>(&self, witx_paths: &[P]) -> Result<String, GenerateError> {
+        let mut out = Vec::new();
+        self.generate_to(witx_paths, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    /// Same as `generate`, except the bindings are streamed into `out`.
+    ///
+    /// Each declaration is handed to `rustfmt` as soon as it's rendered and the
+    /// formatted output is copied straight into `out`, so neither the raw nor
+    /// the formatted source is ever held in memory all at once.
+    pub fn generate_to<P: AsRef<Path>, W: Write>(
+        &self,
+        witx_paths: &[P],
+        out: &mut W,
+    ) -> Result<(), GenerateError> {
+        self.render_to(&load(witx_paths)?, out)
+    }
+
+    /// Same as `generate`, except the witx document is parsed from `contents`
+    /// rather than loaded from files.
+    ///
+    /// As the document has no path, it can't `use` any other documents.
+    pub fn generate_from_str(&self, contents: &str) -> Result<String, GenerateError> {
+        let mut out = Vec::new();
+        self.render_to(&witx::parse(contents)?, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn render_to<W: Write>(&self, doc: &Document, out: &mut W) -> Result<(), GenerateError> {
+        let mut rustfmt = Rustfmt::spawn()?;
+        rustfmt.write_all(self.file_header().as_bytes())?;
+        // Only import what's used, so that the bindings don't trip the
+        // `unused_imports` lint for documents without enums or return
+        // pointers.
+        let modules = self.modules(doc);
+        if modules
+            .iter()
+            .any(|m| self.funcs(m).iter().any(|f| uses_return_pointer(f)))
+        {
+            rustfmt.write_all(b"use core::mem::MaybeUninit;\n")?;
+        }
+        if doc.typenames().any(|ty| uses_fmt(&ty)) {
+            rustfmt.write_all(b"use core::fmt;\n")?;
+        }
+        let mut raw = String::new();
+        for ty in self.typenames(doc) {
+            render_typename(self, &ty, &mut raw)?;
+            raw.push('\n');
+            rustfmt.write_all(raw.as_bytes())?;
+            raw.clear();
+        }
+        let mut skipped = Vec::new();
+        for m in modules {
+            skipped.extend(render_module(self, &m, &mut raw)?);
+            raw.push('\n');
+            rustfmt.write_all(raw.as_bytes())?;
+            raw.clear();
+        }
+        for c in self.constants(doc) {
+            render_constant(self, doc, c, &mut raw);
+            rustfmt.write_all(raw.as_bytes())?;
+            raw.clear();
+        }
+        render_skipped(&skipped, &mut raw);
+        rustfmt.write_all(raw.as_bytes())?;
+
+        Ok(rustfmt.finish(out)?)
+    }
+
+    /// Returns whether `existing` is exactly what `generate` produces for
+    /// `witx_paths`, i.e. whether checked-in bindings are up to date.
+    pub fn check<P: AsRef<Path>>(
+        &self,
+        witx_paths: &[P],
+        existing: &str,
+    ) -> Result<bool, GenerateError> {
+        Ok(self.generate(witx_paths)? == existing)
+    }
+
+    /// Generates bindings for several layered witx documents as a set of
+    /// files.
+    ///
+    /// The first file returned is always `types.rs`, which contains every type
+    /// and constant defined across all of `witx_paths`. It's followed by one
+    /// file per input document, named after the document, which contains only
+    /// the functions of that document's modules. Each of those files imports
+    /// the types its functions reference from a sibling `types` module, so the
+    /// files are expected to be included as modules next to each other, for
+    /// example:
+    ///
+    /// ```text
+    /// pub mod types { include!(concat!(env!("OUT_DIR"), "/types.rs")); }
+    /// pub mod wasi_ephemeral_fd { include!(concat!(env!("OUT_DIR"), "/wasi_ephemeral_fd.rs")); }
+    /// ```
+    pub fn generate_files<P: AsRef<Path>>(
+        &self,
+        witx_paths: &[P],
+    ) -> Result<Vec<(String, String)>, GenerateError> {
+        let doc = load(witx_paths)?;
+        let mut files = vec![(
+            format!("{}.rs", TYPES_MODULE),
+            render_types_file(self, &doc)?,
+        )];
+        for path in witx_paths {
+            let path = path.as_ref();
+            let doc = load(&[path])?;
+            let name = file_stem(path)?.to_snake_case();
+            let modules = self.modules(&doc);
+            files.push((
+                format!("{}.rs", name),
+                render_functions_file(self, &modules)?,
+            ));
+        }
+        Ok(files)
+    }
+
+    /// Same as `generate_files`, except functions are split up by module
+    /// rather than by document.
+    ///
+    /// This is useful for a single document declaring several modules. Each
+    /// module's file is named after the module, e.g.
+    /// `wasi_snapshot_preview1.rs`, and `types.rs` again holds every type and
+    /// constant.
+    pub fn generate_module_files<P: AsRef<Path>>(
+        &self,
+        witx_paths: &[P],
+    ) -> Result<Vec<(String, String)>, GenerateError> {
+        let doc = load(witx_paths)?;
+        let mut files = vec![(
+            format!("{}.rs", TYPES_MODULE),
+            render_types_file(self, &doc)?,
+        )];
+        for m in self.modules(&doc) {
+            let name = m.name.as_str().to_snake_case();
+            files.push((format!("{}.rs", name), render_functions_file(self, &[m])?));
+        }
+        Ok(files)
+    }
+
+    fn file_header(&self) -> String {
+        let mut header = HEADER.to_string();
+        if let Some(custom) = &self.header {
+            header.push_str(custom);
+            if !custom.ends_with('\n') {
+                header.push('\n');
+            }
+        }
+        header
+    }
+
+    fn typenames(&self, doc: &Document) -> Vec<Rc<NamedType>> {
+        self.order(doc.typenames(), |ty| ty.name.as_str().to_string())
+    }
+
+    fn modules(&self, doc: &Document) -> Vec<Rc<Module>> {
+        let modules = doc
+            .modules()
+            .filter(|m| self.modules.matches(m.name.as_str()));
+        self.order(modules, |m| m.name.as_str().to_string())
+    }
+
+    fn funcs(&self, m: &Module) -> Vec<Rc<InterfaceFunc>> {
+        let funcs = m
+            .funcs()
+            .filter(|f| self.functions.matches(f.name.as_str()));
+        self.order(funcs, |f| f.name.as_str().to_string())
+    }
+
+    fn constants<'a>(&self, doc: &'a Document) -> Vec<&'a Constant> {
+        self.order(doc.constants(), |c| {
+            format!("{}::{}", c.ty.as_str(), c.name.as_str())
+        })
+    }
+
+    /// Collects `items`, sorted by `name` if `sorted` is enabled.
+    fn order<T>(&self, items: impl Iterator<Item = T>, name: impl Fn(&T) -> String) -> Vec<T> {
+        let mut items = items.collect::<Vec<_>>();
+        if self.sorted {
+            items.sort_by_key(name);
+        }
+        items
+    }
+}
+
+/// Names allowed and denied by a `GenerateConfig`.
+#[derive(Debug, Clone, Default)]
+struct Filter {
+    allow: BTreeSet<String>,
+    deny: BTreeSet<String>,
+}
+
+impl Filter {
+    /// Returns whether `name` should be generated: it has to be allowed, if
+    /// anything is, and mustn't be denied.
+    fn matches(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.contains(name)) && !self.deny.contains(name)
+    }
+}
+
+/// Generates bindings for `witx_paths` with the default `GenerateConfig`.
+pub fn generate<P: AsRef<Path>>(witx_paths: &[P]) -> Result<String, GenerateError> {
+    GenerateConfig::default().generate(witx_paths)
+}
+
+/// Generates bindings for `witx_paths` as configured by `config`.
+pub fn generate_with<P: AsRef<Path>>(
+    config: &GenerateConfig,
+    witx_paths: &[P],
+) -> Result<String, GenerateError> {
+    config.generate(witx_paths)
+}
+
+/// See `GenerateConfig::generate_to`.
+pub fn generate_to<P: AsRef<Path>, W: Write>(
+    witx_paths: &[P],
+    out: &mut W,
+) -> Result<(), GenerateError> {
+    GenerateConfig::default().generate_to(witx_paths, out)
+}
+
+/// See `GenerateConfig::generate_from_str`.
+pub fn generate_from_str(contents: &str) -> Result<String, GenerateError> {
+    GenerateConfig::default().generate_from_str(contents)
+}
+
+/// See `GenerateConfig::check`.
+pub fn check<P: AsRef<Path>>(witx_paths: &[P], existing: &str) -> Result<bool, GenerateError> {
+    GenerateConfig::default().check(witx_paths, existing)
+}
+
+/// See `GenerateConfig::generate_files`.
+pub fn generate_files<P: AsRef<Path>>(
+    witx_paths: &[P],
+) -> Result<Vec<(String, String)>, GenerateError> {
+    GenerateConfig::default().generate_files(witx_paths)
+}
+
+/// See `GenerateConfig::generate_module_files`.
+pub fn generate_module_files<P: AsRef<Path>>(
+    witx_paths: &[P],
+) -> Result<Vec<(String, String)>, GenerateError> {
+    GenerateConfig::default().generate_module_files(witx_paths)
+}
+
+/// Loads `witx_paths`, first checking that they all name files as witx
+/// expects them to.
+fn load<P: AsRef<Path>>(witx_paths: &[P]) -> Result<Document, GenerateError> {
+    for path in witx_paths {
+        file_stem(path.as_ref())?;
+    }
+    Ok(witx::load(witx_paths)?)
+}
+
+/// Returns the name of the file `path` names, without its extension.
+fn file_stem(path: &Path) -> io::Result<Cow<'_, str>> {
+    match path.file_stem() {
+        Some(stem) => Ok(stem.to_string_lossy()),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("witx path `{}` doesn't name a file", path.display()),
+        )),
+    }
+}
+
+/// Renders the shared `types.rs` of `generate_files`.
+fn render_types_file(config: &GenerateConfig, doc: &Document) -> Result<String, GenerateError> {
+    let mut raw = String::new();
+    raw.push_str(&config.file_header());
+    if doc.typenames().any(|ty| uses_fmt(&ty)) {
+        raw.push_str("use core::fmt;\n");
+    }
+    for ty in config.typenames(doc) {
+        render_typename(config, &ty, &mut raw)?;
+        raw.push('\n');
+    }
+    for c in config.constants(doc) {
+        render_constant(config, doc, c, &mut raw);
+    }
+    Ok(rustfmt(&raw)?)
+}
+
+/// Renders the functions of `modules` as one file, importing the types they
+/// use from the sibling `types` module.
+fn render_functions_file(
+    config: &GenerateConfig,
+    modules: &[Rc<Module>],
+) -> Result<String, GenerateError> {
+    let mut types = BTreeSet::new();
+    let mut return_pointers = false;
+    for m in modules {
+        referenced_types(config, m, &mut types);
+        return_pointers |= config.funcs(m).iter().any(|f| uses_return_pointer(f));
+    }
+    let mut raw = String::new();
+    raw.push_str(&config.file_header());
+    if return_pointers {
+        raw.push_str("use core::mem::MaybeUninit;\n");
+    }
+    if !types.is_empty() {
+        raw.push_str("use super::");
+        raw.push_str(TYPES_MODULE);
+        raw.push_str("::{");
+        raw.push_str(&types.into_iter().collect::<Vec<_>>().join(", "));
+        raw.push_str("};\n");
+    }
+    let mut skipped = Vec::new();
+    for m in modules {
+        skipped.extend(render_module(config, m, &mut raw)?);
+        raw.push('\n');
+    }
+    render_skipped(&skipped, &mut raw);
+    Ok(rustfmt(&raw)?)
+}
+
+fn render_constant(config: &GenerateConfig, doc: &Document, c: &Constant, src: &mut String) {
+    rustdoc(&c.docs, src);
+    let ty = doc.typename(&c.ty);
+    // witx constants are always parsed as a `u64`, so values of signed types
+    // are reinterpreted at the type's width to get their sign back.
+    let signed = match ty.as_ref().map(|t| &**t.type_()) {
+        Some(Type::Builtin(BuiltinType::S8)) => Some(c.value as i8 as i64),
+        Some(Type::Builtin(BuiltinType::S16)) => Some(c.value as i16 as i64),
+        Some(Type::Builtin(BuiltinType::S32)) => Some(c.value as i32 as i64),
+        Some(Type::Builtin(BuiltinType::S64)) => Some(c.value as i64),
+        _ => None,
+    };
+    let mut value = match (signed, config.hex_constants) {
+        (Some(v), true) if v < 0 => format!("-{:#x}", v.unsigned_abs()),
+        (Some(v), false) => v.to_string(),
+        (_, true) => format!("{:#x}", c.value),
+        (None, false) => c.value.to_string(),
+    };
+    // witx only has integer literals, which Rust won't accept for floats.
+    if let Some(Type::Builtin(BuiltinType::F32 | BuiltinType::F64)) =
+        ty.as_ref().map(|t| &**t.type_())
+    {
+        value = format!("{}.0", c.value);
+    }
+    if let Some(newtype) = ty.and_then(|ty| newtype_alias(config, &TypeRef::Name(ty))) {
+        value = format!("{}({})", newtype, value);
+    }
+    src.push_str(&format!(
+        "pub const {}: {} = {};\n",
+        const_ident(c.ty.as_str(), c.name.as_str()),
+        type_ident(c.ty.as_str()),
+        value
+    ));
+}
+
+fn rustfmt(raw: &str) -> io::Result<String> {
+    let mut out = Vec::new();
+    let mut rustfmt = Rustfmt::spawn()?;
+    rustfmt.write_all(raw.as_bytes())?;
+    rustfmt.finish(&mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+/// A `rustfmt` process formatting all source written to it.
+struct Rustfmt {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl Rustfmt {
+    fn spawn() -> io::Result<Rustfmt> {
+        let mut child = Command::new("rustfmt")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        Ok(Rustfmt { child, stdin })
+    }
+
+    /// Closes `rustfmt`'s input and copies the formatted source into `out`.
+    ///
+    /// `rustfmt` reads all of its input before writing anything, so it's fine
+    /// to only start draining its output here.
+    fn finish<W: Write>(mut self, out: &mut W) -> io::Result<()> {
+        drop(self.stdin.take());
+        io::copy(self.child.stdout.as_mut().unwrap(), out)?;
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("rustfmt failed: {}", status)));
+        }
+        Ok(())
+    }
+}
+
+/// Reaps `rustfmt` when formatting is abandoned, e.g. because writing to it
+/// failed, rather than leaving it running or as a zombie.
+impl Drop for Rustfmt {
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+impl Write for Rustfmt {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdin.as_mut().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdin.as_mut().unwrap().flush()
+    }
 }
 
 trait Render {
     fn render(&self, src: &mut String);
 }
 
-impl Render for NamedType {
-    fn render(&self, src: &mut String) {
-        let name = self.name.as_str();
-        match &self.tref {
-            TypeRef::Value(ty) => match &**ty {
-                Type::Record(s) => render_record(src, name, s),
-                Type::Handle(h) => render_handle(src, name, h),
-                Type::Variant(h) => render_variant(src, name, h),
-                Type::List { .. }
-                | Type::Pointer { .. }
-                | Type::ConstPointer { .. }
-                | Type::Builtin { .. } => render_alias(src, name, &self.tref),
-            },
-            TypeRef::Name(_nt) => render_alias(src, name, &self.tref),
-        }
+fn render_typename(
+    config: &GenerateConfig,
+    ty: &NamedType,
+    src: &mut String,
+) -> Result<(), GenerateError> {
+    let name = ty.name.as_str();
+    // Only the type being defined can be a record or variant of its own, so
+    // it's everything it's made of that has to be renderable.
+    let parts = match &ty.tref {
+        TypeRef::Value(t) => match &**t {
+            Type::Record(s) => s.members.iter().map(|m| &m.tref).collect(),
+            Type::Variant(v) => v.cases.iter().filter_map(|c| c.tref.as_ref()).collect(),
+            Type::Handle(_) => Vec::new(),
+            _ => vec![&ty.tref],
+        },
+        TypeRef::Name(_) => Vec::new(),
+    };
+    if let Err(what) = parts.into_iter().try_for_each(check_renderable) {
+        return Err(GenerateError::Unsupported(format!(
+            "type `{}` contains {}, which is not supported by witx-bindgen (witx {})",
+            name, what, WITX_VERSION
+        )));
+    }
+    match &ty.tref {
+        TypeRef::Value(t) => match &**t {
+            Type::Record(s) => return render_record(src, name, s),
+            Type::Handle(h) => render_handle(config, src, name, h),
+            Type::Variant(h) => render_variant(src, name, h),
+            Type::List { .. }
+            | Type::Pointer { .. }
+            | Type::ConstPointer { .. }
+            | Type::Builtin { .. } => render_alias(config, src, name, &ty.tref),
+        },
+        TypeRef::Name(_nt) => render_alias(config, src, name, &ty.tref),
     }
+    Ok(())
 }
 
-fn render_record(src: &mut String, name: &str, s: &RecordDatatype) {
+fn render_record(src: &mut String, name: &str, s: &RecordDatatype) -> Result<(), GenerateError> {
     if let Some(repr) = s.bitflags_repr() {
-        src.push_str(&format!("pub type {} = ", name.to_camel_case()));
+        src.push_str(&format!("pub type {} = ", type_ident(name)));
         repr.render(src);
         src.push(';');
         for (i, member) in s.members.iter().enumerate() {
             rustdoc(&member.docs, src);
             src.push_str(&format!(
-                "pub const {}_{}: {} = 1 << {};\n",
-                name.to_shouty_snake_case(),
-                member.name.as_str().to_shouty_snake_case(),
-                name.to_camel_case(),
+                "pub const {}: {} = 1 << {};\n",
+                const_ident(name, member.name.as_str()),
+                type_ident(name),
                 i,
             ));
         }
-        return;
+        return Ok(());
     }
     src.push_str("#[repr(C)]\n");
     if record_contains_union(s) {
@@ -107,63 +681,116 @@ fn render_record(src: &mut String, name: &str, s: &RecordDatatype) {
     } else {
         src.push_str("#[derive(Copy, Clone, Debug)]\n");
     }
-    src.push_str(&format!("pub struct {} {{\n", name.to_camel_case()));
+    let lifetime = if record_borrows(s) { "<'a>" } else { "" };
+    src.push_str(&format!("pub struct {}{} {{\n", type_ident(name), lifetime));
     for member in s.members.iter() {
         rustdoc(&member.docs, src);
         src.push_str("pub ");
         member.name.render(src);
         src.push_str(": ");
-        member.tref.render(src);
+        match &**member.tref.type_() {
+            // Rust doesn't specify the layout of slice references, so a list
+            // is laid out as a pointer to its elements followed by its length,
+            // like the `buf` and `buf_len` of a `ciovec`.
+            Type::List(elem) => {
+                let field = to_rust_ident(member.name.as_str());
+                let len = format!("{}_len", member.name.as_str());
+                let len = to_rust_ident(&len);
+                if s.members
+                    .iter()
+                    .any(|m| to_rust_ident(m.name.as_str()) == len)
+                {
+                    return Err(GenerateError::Unsupported(format!(
+                        "record `{}` has a member `{}`, which clashes with the length field of `{}`",
+                        name,
+                        len,
+                        member.name.as_str()
+                    )));
+                }
+                src.push_str("*const ");
+                render_list_elem(elem, src);
+                src.push_str(",\n");
+                src.push_str(&format!(
+                    "/// The number of elements in `{}`.\npub {}: usize",
+                    field, len,
+                ));
+            }
+            _ => render_borrowed(&member.tref, src),
+        }
         src.push_str(",\n");
     }
-    src.push_str("}");
+    src.push('}');
+    Ok(())
+}
+
+/// Renders the type a list member's pointer points to, where the elements of
+/// a string are its UTF-8 bytes.
+fn render_list_elem(elem: &TypeRef, src: &mut String) {
+    match &**elem.type_() {
+        Type::Builtin(BuiltinType::Char) => src.push_str("u8"),
+        _ => render_borrowed(elem, src),
+    }
 }
 
 fn render_variant(src: &mut String, name: &str, v: &Variant) {
     if v.cases.iter().all(|c| c.tref.is_none()) {
         return render_enum_like_variant(src, name, v);
     }
+    let lifetime = if variant_borrows(v) { "<'a>" } else { "" };
     src.push_str("#[repr(C)]\n");
     src.push_str("#[derive(Copy, Clone)]\n");
-    src.push_str(&format!("pub union {}U {{\n", name.to_camel_case()));
+    src.push_str(&format!("pub union {}U{} {{\n", type_ident(name), lifetime));
     for case in v.cases.iter() {
         if let Some(ref tref) = case.tref {
             rustdoc(&case.docs, src);
+            // A union field can't be split into a pointer and a length like a
+            // record member, so say what the slice reference assumes.
+            if let Type::List(_) = &**tref.type_() {
+                if !case.docs.trim().is_empty() {
+                    src.push_str("///\n");
+                }
+                src.push_str(
+                    "/// This assumes a slice reference is laid out as a pointer followed by\n\
+                     /// a length, which Rust doesn't guarantee.\n",
+                );
+            }
             src.push_str("pub ");
             case.name.render(src);
             src.push_str(": ");
-            tref.render(src);
+            render_borrowed(tref, src);
             src.push_str(",\n");
         }
     }
     src.push_str("}\n");
     src.push_str("#[repr(C)]\n");
     src.push_str("#[derive(Copy, Clone)]\n");
-    src.push_str(&format!("pub struct {} {{\n", name.to_camel_case()));
+    src.push_str(&format!("pub struct {}{} {{\n", type_ident(name), lifetime));
     src.push_str("pub tag: ");
     v.tag_repr.render(src);
     src.push_str(",\n");
-    src.push_str(&format!("pub u: {}U,\n", name.to_camel_case()));
+    src.push_str(&format!("pub u: {}U{},\n", type_ident(name), lifetime));
     src.push_str("}\n");
 }
 
 fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
     src.push_str("#[repr(transparent)]\n");
     src.push_str("#[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]\n");
-    src.push_str(&format!("pub struct {}(", name.to_camel_case()));
+    src.push_str(&format!("pub struct {}(pub(crate) ", type_ident(name)));
     s.tag_repr.render(src);
     src.push_str(");\n");
     for (i, variant) in s.cases.iter().enumerate() {
         rustdoc(&variant.docs, src);
         src.push_str(&format!(
-            "pub const {}_{}: {ty} = {ty}({});\n",
-            name.to_shouty_snake_case(),
-            variant.name.as_str().to_shouty_snake_case(),
+            "pub const {}: {ty} = {ty}({});\n",
+            const_ident(name, variant.name.as_str()),
             i,
-            ty = name.to_camel_case(),
+            ty = type_ident(name),
         ));
     }
-    let camel_name = name.to_camel_case();
+    let camel_name = type_ident(name);
+    // Once every value of the tag is a case, comparing against the number of
+    // cases may overflow the tag type, and catch-all arms are unreachable.
+    let exhaustive = s.cases.len() as u128 >= tag_values(s.tag_repr);
 
     src.push_str("impl ");
     src.push_str(&camel_name);
@@ -173,6 +800,24 @@ fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
     s.tag_repr.render(src);
     src.push_str("{ self.0 }\n\n");
 
+    // The cases are numbered from zero, so anything past the last case isn't
+    // a valid value.
+    src.push_str("pub const fn from_raw(raw: ");
+    s.tag_repr.render(src);
+    src.push_str(") -> Option<");
+    src.push_str(&camel_name);
+    src.push_str("> {\n");
+    if exhaustive {
+        src.push_str(&format!("Some({}(raw))\n", camel_name));
+    } else {
+        src.push_str(&format!(
+            "if raw < {} {{ Some({}(raw)) }} else {{ None }}\n",
+            s.cases.len(),
+            camel_name
+        ));
+    }
+    src.push_str("}\n\n");
+
     src.push_str("pub fn name(&self) -> &'static str {\n");
     src.push_str("match self.0 {");
     for (i, variant) in s.cases.iter().enumerate() {
@@ -181,7 +826,11 @@ fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
         src.push_str(&variant.name.as_str().to_shouty_snake_case());
         src.push_str("\",");
     }
-    src.push_str("_ => unsafe { core::hint::unreachable_unchecked() },");
+    // The host may return tags which this version of the bindings doesn't
+    // know about yet, so those can't be assumed away.
+    if !exhaustive {
+        src.push_str("_ => \"UNKNOWN\",");
+    }
     src.push_str("}\n");
     src.push_str("}\n");
 
@@ -193,7 +842,9 @@ fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
         src.push_str(variant.docs.trim());
         src.push_str("\",");
     }
-    src.push_str("_ => unsafe { core::hint::unreachable_unchecked() },");
+    if !exhaustive {
+        src.push_str("_ => \"\",");
+    }
     src.push_str("}\n");
     src.push_str("}\n");
 
@@ -223,7 +874,7 @@ fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
         src.push_str("write!(f, \"{} (error {})\", self.name(), self.0)");
         src.push_str("}\n");
         src.push_str("}\n");
-        src.push_str("\n");
+        src.push('\n');
         src.push_str("#[cfg(feature = \"std\")]\n");
         src.push_str("extern crate std;\n");
         src.push_str("#[cfg(feature = \"std\")]\n");
@@ -233,6 +884,16 @@ fn render_enum_like_variant(src: &mut String, name: &str, s: &Variant) {
     }
 }
 
+/// Returns how many values a tag of type `repr` can hold.
+fn tag_values(repr: IntRepr) -> u128 {
+    match repr {
+        IntRepr::U8 => 1 << 8,
+        IntRepr::U16 => 1 << 16,
+        IntRepr::U32 => 1 << 32,
+        IntRepr::U64 => 1 << 64,
+    }
+}
+
 impl Render for IntRepr {
     fn render(&self, src: &mut String) {
         match self {
@@ -244,9 +905,16 @@ impl Render for IntRepr {
     }
 }
 
-fn render_alias(src: &mut String, name: &str, dest: &TypeRef) {
-    src.push_str(&format!("pub type {}", name.to_camel_case()));
-    if let Type::List(_) = &**dest.type_() {
+fn render_alias(config: &GenerateConfig, src: &mut String, name: &str, dest: &TypeRef) {
+    if let TypeRef::Value(ty) = dest {
+        if let Type::Builtin(_) = &**ty {
+            if config.newtype_aliases {
+                return render_newtype_alias(src, name, dest);
+            }
+        }
+    }
+    src.push_str(&format!("pub type {}", type_ident(name)));
+    if type_borrows(dest.type_()) {
         src.push_str("<'a>");
     }
     src.push_str(" = ");
@@ -257,30 +925,99 @@ fn render_alias(src: &mut String, name: &str, dest: &TypeRef) {
     if name == "size" {
         src.push_str("usize");
     } else {
-        dest.render(src);
+        render_borrowed(dest, src);
     }
     src.push(';');
 }
 
+/// Renders `tref` for use in a type definition, which can't elide lifetimes,
+/// so anything it borrows is tied to the definition's `'a` lifetime instead.
+fn render_borrowed(tref: &TypeRef, src: &mut String) {
+    let mut ty = String::new();
+    match tref {
+        TypeRef::Value(v) => match &**v {
+            Type::List(t) => render_list(&mut ty, t, "'a "),
+            _ => tref.render(&mut ty),
+        },
+        TypeRef::Name(_) => tref.render(&mut ty),
+    }
+    src.push_str(&ty.replace("'_", "'a"));
+}
+
+/// Renders an alias to a builtin as a distinct newtype rather than a
+/// transparent `type` alias, so e.g. a `Filesize` can't be passed where a
+/// `Timestamp` is expected.
+fn render_newtype_alias(src: &mut String, name: &str, dest: &TypeRef) {
+    src.push_str("#[repr(transparent)]\n");
+    match &**dest.type_() {
+        // Floats are only partially ordered, and can't be hashed.
+        Type::Builtin(BuiltinType::F32 | BuiltinType::F64) => {
+            src.push_str("#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]\n")
+        }
+        _ => src.push_str("#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]\n"),
+    }
+    src.push_str(&format!("pub struct {}(pub ", type_ident(name)));
+    // See `render_alias` for why `size` is special.
+    if name == "size" {
+        src.push_str("usize");
+    } else {
+        dest.render(src);
+    }
+    src.push_str(");");
+}
+
+/// If newtype aliases are enabled and `tref` names an alias to a builtin,
+/// returns the name of the newtype struct it resolves to.
+///
+/// Aliases of aliases are still plain `type` aliases of that struct, and
+/// those can't be used as tuple struct constructors, hence the resolution.
+fn newtype_alias(config: &GenerateConfig, tref: &TypeRef) -> Option<String> {
+    if !config.newtype_aliases {
+        return None;
+    }
+    match tref {
+        TypeRef::Name(nt) => match &nt.tref {
+            TypeRef::Value(ty) => match &**ty {
+                Type::Builtin(_) => Some(type_ident(nt.name.as_str())),
+                _ => None,
+            },
+            inner => newtype_alias(config, inner),
+        },
+        TypeRef::Value(_) => None,
+    }
+}
+
+/// Renders a `list` as the borrowed slice it's passed as, where `list<char>`
+/// is a UTF-8 `str` and every other element type (including `char8`) is a
+/// plain slice of that element.
+fn render_list(src: &mut String, elem: &TypeRef, lifetime: &str) {
+    src.push('&');
+    src.push_str(lifetime);
+    match &**elem.type_() {
+        Type::Builtin(BuiltinType::Char) => src.push_str("str"),
+        inner => {
+            src.push('[');
+            match (elem, inner) {
+                (TypeRef::Value(_), Type::List(t)) => render_list(src, t, lifetime),
+                _ => elem.render(src),
+            }
+            src.push(']');
+        }
+    }
+}
+
 impl Render for TypeRef {
     fn render(&self, src: &mut String) {
         match self {
             TypeRef::Name(t) => {
-                src.push_str(&t.name.as_str().to_camel_case());
-                if let Type::List(_) = &**t.type_() {
+                src.push_str(&type_ident(t.name.as_str()));
+                if type_borrows(t.type_()) {
                     src.push_str("<'_>");
                 }
             }
             TypeRef::Value(v) => match &**v {
                 Type::Builtin(t) => t.render(src),
-                Type::List(t) => match &**t.type_() {
-                    Type::Builtin(BuiltinType::Char) => src.push_str("&str"),
-                    _ => {
-                        src.push_str("&'a [");
-                        t.render(src);
-                        src.push_str("]");
-                    }
-                },
+                Type::List(t) => render_list(src, t, ""),
                 Type::Pointer(t) => {
                     src.push_str("*mut ");
                     t.render(src);
@@ -297,26 +1034,24 @@ impl Render for TypeRef {
                             Some(ty) => ty.render(src),
                             None => src.push_str("()"),
                         }
-                        src.push_str(",");
+                        src.push(',');
                         match err {
                             Some(ty) => ty.render(src),
                             None => src.push_str("()"),
                         }
-                        src.push_str(">");
-                    }
-                    None => {
-                        panic!("unsupported anonymous variant")
+                        src.push('>');
                     }
+                    None => unreachable!("rejected by `check_renderable`"),
                 },
                 Type::Record(r) if r.is_tuple() => {
-                    src.push_str("(");
+                    src.push('(');
                     for member in r.members.iter() {
                         member.tref.render(src);
-                        src.push_str(",");
+                        src.push(',');
                     }
-                    src.push_str(")");
+                    src.push(')');
                 }
-                t => panic!("reference to anonymous {} not possible!", t.kind()),
+                _ => unreachable!("rejected by `check_renderable`"),
             },
         }
     }
@@ -348,107 +1083,256 @@ impl Render for BuiltinType {
     }
 }
 
-impl Render for Module {
-    fn render(&self, src: &mut String) {
-        // wrapper functions
-        for f in self.funcs() {
-            render_highlevel(&f, &self.name, src);
-            src.push_str("\n\n");
-        }
-
-        // raw module
-        let rust_name = self.name.as_str().to_snake_case();
+/// Renders the wrappers and raw imports of `m`, returning the functions which
+/// were skipped in lenient mode.
+fn render_module(
+    config: &GenerateConfig,
+    m: &Module,
+    src: &mut String,
+) -> Result<Vec<String>, GenerateError> {
+    let rust_name = m.name.as_str().to_snake_case();
+    if config.module_namespaces {
         src.push_str("pub mod ");
         src.push_str(&rust_name);
         src.push_str("{\n");
-        src.push_str("#[link(wasm_import_module =\"");
-        src.push_str(self.name.as_str());
-        src.push_str("\")]\n");
-        src.push_str("extern \"C\" {\n");
-        for f in self.funcs() {
-            f.render(src);
-            src.push_str("\n");
+        // A glob import which nothing uses still trips `unused_imports`.
+        let mut types = BTreeSet::new();
+        referenced_types(config, m, &mut types);
+        if !types.is_empty() {
+            src.push_str("use super::*;\n");
+        }
+    }
+
+    // wrapper functions
+    let mut funcs = Vec::new();
+    let mut skipped = Vec::new();
+    for f in config.funcs(m) {
+        match check_supported(&f) {
+            Ok(()) => {
+                render_highlevel(config, &f, &m.name, src);
+                src.push_str("\n\n");
+                funcs.push(f);
+            }
+            Err(reason) if config.lenient => {
+                src.push_str(&format!("// TODO: skipped because {}\n\n", reason));
+                skipped.push(format!("{}::{}", m.name.as_str(), f.name.as_str()));
+            }
+            Err(reason) => return Err(GenerateError::Unsupported(reason)),
+        }
+    }
+    if config.syscall_traits {
+        render_syscalls_trait(config, m, &funcs, src);
+    }
+
+    // raw module
+    src.push_str("pub mod ");
+    src.push_str(if config.module_namespaces {
+        "raw"
+    } else {
+        &rust_name
+    });
+    src.push_str("{\n");
+    src.push_str("#[link(wasm_import_module =\"");
+    src.push_str(m.name.as_str());
+    src.push_str("\")]\n");
+    src.push_str("extern \"C\" {\n");
+    for f in config.funcs(m) {
+        // Lenient mode has already left a comment for the wrapper.
+        if check_wasm_results(&f).is_err() {
+            continue;
         }
-        src.push_str("}");
-        src.push_str("}");
+        f.render(src);
+        src.push('\n');
     }
+    src.push('}');
+    src.push('}');
+    if config.module_namespaces {
+        src.push('}');
+    }
+    Ok(skipped)
 }
 
-fn render_highlevel(func: &InterfaceFunc, module: &Id, src: &mut String) {
-    let mut rust_name = String::new();
-    func.name.render(&mut rust_name);
-    let rust_name = rust_name.to_snake_case();
+/// Renders a closing comment listing the functions skipped in lenient mode.
+fn render_skipped(skipped: &[String], src: &mut String) {
+    if skipped.is_empty() {
+        return;
+    }
+    src.push_str("\n// The following functions were skipped as they aren't supported yet:\n");
+    for name in skipped {
+        src.push_str(&format!("// - `{}`\n", name));
+    }
+}
+
+fn render_highlevel(config: &GenerateConfig, func: &InterfaceFunc, module: &Id, src: &mut String) {
     rustdoc(&func.docs, src);
     rustdoc_params(&func.params, "Parameters", src);
     rustdoc_params(&func.results, "Return", src);
 
-    // Render the function and its arguments, and note that the arguments here
-    // are the exact type name arguments as opposed to the pointer/length pair
-    // ones. These functions are unsafe because they work with integer file
+    // These functions are unsafe because they work with integer file
     // descriptors, which are effectively forgeable and danglable raw pointers
     // into the file descriptor address space.
     src.push_str("pub unsafe fn ");
+    render_signature(config, func, module, false, src);
+    src.push('{');
+
+    func.call_wasm(
+        module,
+        &mut Rust {
+            config,
+            src,
+            name: func.name.as_str(),
+            params: &func.params,
+            results: &func.results,
+            block_storage: Vec::new(),
+            blocks: Vec::new(),
+            ret: None,
+        },
+    );
+
+    src.push('}');
+}
+
+/// Returns the name of the wrapper function generated for `func`.
+fn wrapper_name(config: &GenerateConfig, func: &InterfaceFunc, module: &Id) -> String {
+    let mut rust_name = String::new();
+    func.name.render(&mut rust_name);
+    let rust_name = rust_name.to_snake_case();
 
     // TODO workout how to handle wasi-ephemeral which introduces multiple
-    // WASI modules into the picture. For now, feature-gate it, and if we're
+    // WASI modules into the picture. For now, make it opt-in, and if we're
     // compiling ephmeral bindings, prefix wrapper syscall with module name.
-    if cfg!(feature = "multi-module") {
-        src.push_str(&[module.as_str().to_snake_case().as_str(), &rust_name].join("_"));
+    if config.multi_module && !config.module_namespaces {
+        [module.as_str().to_snake_case().as_str(), &rust_name].join("_")
     } else {
-        src.push_str(to_rust_ident(&rust_name));
+        to_rust_ident(&rust_name).into_owned()
     }
+}
 
-    src.push_str("(");
-    for param in func.params.iter() {
-        param.name.render(src);
-        src.push_str(": ");
-        param.tref.render(src);
-        src.push_str(",");
-    }
-    src.push_str(")");
+/// Renders the name, arguments and return type of the wrapper for `func`,
+/// taking `&self` first if `receiver` is set.
+///
+/// The arguments here are the exact type name arguments as opposed to the
+/// pointer/length pair ones.
+fn render_signature(
+    config: &GenerateConfig,
+    func: &InterfaceFunc,
+    module: &Id,
+    receiver: bool,
+    src: &mut String,
+) {
+    src.push_str(&wrapper_name(config, func, module));
 
+    let mut ret = String::new();
     match func.results.len() {
+        // The raw import already returns `!`, so the call is the last thing
+        // the wrapper does and it can return `!` as well.
+        0 if func.noreturn => ret.push_str(" -> !"),
         0 => {}
         1 => {
-            src.push_str(" -> ");
-            func.results[0].tref.render(src);
+            ret.push_str(" -> ");
+            func.results[0].tref.render(&mut ret);
         }
         _ => {
-            src.push_str(" -> (");
+            ret.push_str(" -> (");
             for result in func.results.iter() {
-                result.tref.render(src);
-                src.push_str(", ");
+                result.tref.render(&mut ret);
+                ret.push_str(", ");
             }
-            src.push_str(")");
+            ret.push(')');
         }
     }
-    src.push_str("{");
+    // Strings and lists returned through a return pointer borrow linear
+    // memory rather than any argument, so there's nothing for an elided
+    // lifetime to refer to. Like `slice::from_raw_parts`, leave it up to the
+    // caller instead.
+    if ret.contains("'_") {
+        ret = ret.replace("'_", "'a");
+        src.push_str("<'a>");
+    }
 
-    func.call_wasm(
-        module,
-        &mut Rust {
-            src,
-            params: &func.params,
-            block_storage: Vec::new(),
-            blocks: Vec::new(),
-        },
-    );
+    src.push('(');
+    if receiver {
+        src.push_str("&self,");
+    }
+    for param in func.params.iter() {
+        param.name.render(src);
+        src.push_str(": ");
+        param.tref.render(src);
+        src.push(',');
+    }
+    src.push(')');
+    src.push_str(&ret);
+}
 
-    src.push_str("}");
+/// Renders a trait with a method for each of `funcs` of `m`, each of which
+/// calls the wrapper function by default.
+fn render_syscalls_trait(
+    config: &GenerateConfig,
+    m: &Module,
+    funcs: &[Rc<InterfaceFunc>],
+    src: &mut String,
+) {
+    src.push_str(&format!(
+        "/// The functions of the `{}` module, as a trait so that they can be\n",
+        m.name.as_str()
+    ));
+    src.push_str("/// substituted, for example in tests.\n");
+    src.push_str("pub trait ");
+    src.push_str(&m.name.as_str().to_camel_case());
+    src.push_str("Syscalls {\n");
+    for f in funcs {
+        src.push_str(&format!(
+            "/// See [`{}`].\n",
+            wrapper_name(config, f, &m.name)
+        ));
+        src.push_str("unsafe fn ");
+        render_signature(config, f, &m.name, true, src);
+        src.push('{');
+        src.push_str(&wrapper_name(config, f, &m.name));
+        src.push('(');
+        for param in f.params.iter() {
+            param.name.render(src);
+            src.push(',');
+        }
+        src.push_str(")}\n");
+    }
+    src.push_str("}\n\n");
 }
 
 struct Rust<'a> {
+    config: &'a GenerateConfig,
     src: &'a mut String,
+    name: &'a str,
     params: &'a [InterfaceFuncParam],
+    results: &'a [InterfaceFuncParam],
     block_storage: Vec<String>,
     blocks: Vec<String>,
+    /// The local which the result of the `CallWasm` is bound to, if any.
+    ret: Option<String>,
+}
+
+impl Rust<'_> {
+    /// Fails generation of the current function on an instruction this
+    /// generator doesn't know how to emit yet.
+    ///
+    /// Apart from `ListFromPointerLength`, which `check_supported` rules out,
+    /// these instructions are only emitted by `call_interface`, so reaching
+    /// this is a bug rather than unsupported input.
+    fn unsupported(&self, inst: &Instruction<'_>) -> ! {
+        let inst = format!("{:?}", inst);
+        let variant = inst.split(|c: char| !c.is_alphanumeric()).next().unwrap();
+        panic!(
+            "instruction `{}` in function `{}` is not supported by witx-bindgen (witx {})",
+            variant, self.name, WITX_VERSION
+        )
+    }
 }
 
 impl Bindgen for Rust<'_> {
     type Operand = String;
 
     fn push_block(&mut self) {
-        let prev = mem::replace(self.src, String::new());
+        let prev = mem::take(self.src);
         self.block_storage.push(prev);
     }
 
@@ -473,7 +1357,11 @@ impl Bindgen for Rust<'_> {
     fn allocate_space(&mut self, n: usize, ty: &witx::NamedType) {
         self.src
             .push_str(&format!("let mut rp{} = MaybeUninit::<", n));
-        self.src.push_str(&ty.name.as_str().to_camel_case());
+        match &**ty.type_() {
+            // See `Instruction::Load` for how lists are read back.
+            Type::List(_) => self.src.push_str("[usize; 2]"),
+            _ => self.src.push_str(&type_ident(ty.name.as_str())),
+        }
         self.src.push_str(">::uninit();");
     }
 
@@ -492,8 +1380,12 @@ impl Bindgen for Rust<'_> {
 
         match inst {
             Instruction::GetArg { nth } => {
+                let param = &self.params[*nth];
                 let mut s = String::new();
-                self.params[*nth].name.render(&mut s);
+                param.name.render(&mut s);
+                if newtype_alias(self.config, &param.tref).is_some() {
+                    s.push_str(".0");
+                }
                 results.push(s);
             }
             Instruction::AddrOf => {
@@ -502,7 +1394,6 @@ impl Bindgen for Rust<'_> {
             Instruction::I64FromBitflags { .. } | Instruction::I64FromU64 => top_as("i64"),
             Instruction::I32FromPointer
             | Instruction::I32FromConstPointer
-            | Instruction::I32FromHandle { .. }
             | Instruction::I32FromUsize
             | Instruction::I32FromChar
             | Instruction::I32FromU8
@@ -512,6 +1403,13 @@ impl Bindgen for Rust<'_> {
             | Instruction::I32FromS16
             | Instruction::I32FromU32
             | Instruction::I32FromBitflags { .. } => top_as("i32"),
+            Instruction::I32FromHandle { .. } => {
+                if self.config.newtype_handles {
+                    results.push(format!("{}.0 as i32", operands[0]));
+                } else {
+                    top_as("i32");
+                }
+            }
 
             Instruction::EnumLower { .. } => {
                 results.push(format!("{}.0 as i32", operands[0]));
@@ -539,26 +1437,55 @@ impl Bindgen for Rust<'_> {
             Instruction::S64FromI64 => {}
             Instruction::U64FromI64 => top_as("u64"),
             Instruction::UsizeFromI32 => top_as("usize"),
-            Instruction::HandleFromI32 { .. } => top_as("u32"),
+            Instruction::HandleFromI32 { ty } => {
+                if self.config.newtype_handles {
+                    results.push(format!("{}({} as u32)", handle_name(ty), operands[0]));
+                } else {
+                    top_as("u32");
+                }
+            }
             Instruction::PointerFromI32 { .. } => top_as("*mut _"),
             Instruction::ConstPointerFromI32 { .. } => top_as("*const _"),
-            Instruction::BitflagsFromI32 { .. } => unimplemented!(),
-            Instruction::BitflagsFromI64 { .. } => unimplemented!(),
+            Instruction::BitflagsFromI32 { ty } | Instruction::BitflagsFromI64 { ty } => {
+                top_as(&type_ident(ty.name.as_str()))
+            }
 
             Instruction::ReturnPointerGet { n } => {
                 results.push(format!("rp{}.as_mut_ptr() as i32", n));
             }
 
-            Instruction::Load { ty } => {
-                let mut s = format!("core::ptr::read({} as *const ", &operands[0]);
-                s.push_str(&ty.name.as_str().to_camel_case());
-                s.push_str(")");
-                results.push(s);
-            }
+            // Rust doesn't specify the layout of slice references, so a list
+            // is read back as the pointer and length the host stored, and the
+            // bytes of a string are checked to be UTF-8.
+            Instruction::Load { ty } => match &**ty.type_() {
+                Type::List(elem) => {
+                    let mut s = format!("{{ let p = {} as *const usize;", &operands[0]);
+                    s.push_str("let list = core::slice::from_raw_parts(");
+                    s.push_str("core::ptr::read(p) as *const _, core::ptr::read(p.add(1)));");
+                    match &**elem.type_() {
+                        Type::Builtin(BuiltinType::Char) => s.push_str(
+                            "core::str::from_utf8(list).expect(\"host returned a string which isn't UTF-8\")",
+                        ),
+                        _ => s.push_str("list"),
+                    }
+                    s.push('}');
+                    results.push(s);
+                }
+                _ => {
+                    let mut s = format!("core::ptr::read({} as *const ", &operands[0]);
+                    s.push_str(&type_ident(ty.name.as_str()));
+                    s.push(')');
+                    results.push(s);
+                }
+            },
 
-            Instruction::ReuseReturn => {
-                results.push("ret".to_string());
-            }
+            Instruction::ReuseReturn => match &self.ret {
+                Some(ret) => results.push(ret.clone()),
+                None => panic!(
+                    "function `{}` reuses the result of a call without one",
+                    self.name
+                ),
+            },
 
             Instruction::TupleLift { .. } => {
                 let value = format!("({})", operands.join(", "));
@@ -575,24 +1502,31 @@ impl Bindgen for Rust<'_> {
                 result.push_str("_ => Err(");
                 result.push_str(&err);
                 result.push_str("),");
-                result.push_str("}");
+                result.push('}');
                 results.push(result);
             }
 
             Instruction::EnumLift { ty } => {
-                let mut result = ty.name.as_str().to_camel_case();
-                result.push_str("(");
+                let mut result = type_ident(ty.name.as_str());
+                result.push('(');
                 result.push_str(&operands[0]);
                 result.push_str(" as ");
                 match &**ty.type_() {
                     Type::Variant(v) => v.tag_repr.render(&mut result),
                     _ => unreachable!(),
                 }
-                result.push_str(")");
+                result.push(')');
                 results.push(result);
             }
 
-            Instruction::CharFromI32 => unimplemented!(),
+            // A `char` which isn't a Unicode scalar value is undefined
+            // behavior, so don't trust the host to return a valid one.
+            Instruction::CharFromI32 => {
+                results.push(format!(
+                    "core::char::from_u32({} as u32).expect(\"host returned an invalid char\")",
+                    operands[0]
+                ));
+            }
 
             Instruction::CallWasm {
                 module,
@@ -600,27 +1534,40 @@ impl Bindgen for Rust<'_> {
                 params: _,
                 results: func_results,
             } => {
-                assert!(func_results.len() < 2);
-                if func_results.len() > 0 {
-                    self.src.push_str("let ret = ");
-                    results.push("ret".to_string());
+                if !func_results.is_empty() {
+                    let ret = "ret".to_string();
+                    self.src.push_str(&format!("let {} = ", ret));
+                    results.push(ret.clone());
+                    self.ret = Some(ret);
+                }
+                if self.config.module_namespaces {
+                    self.src.push_str("raw");
+                } else {
+                    self.src.push_str(&module.to_snake_case());
                 }
-                self.src.push_str(&module.to_snake_case());
                 self.src.push_str("::");
-                self.src.push_str(to_rust_ident(&name.to_snake_case()));
-                self.src.push_str("(");
+                self.src.push_str(&to_rust_ident(&name.to_snake_case()));
+                self.src.push('(');
                 self.src.push_str(&operands.join(", "));
                 self.src.push_str(");");
             }
 
             Instruction::Return { amt: 0 } => {}
             Instruction::Return { amt: 1 } => {
-                self.src.push_str(&operands[0]);
+                match newtype_alias(self.config, &self.results[0].tref) {
+                    Some(newtype) => {
+                        self.src.push_str(&newtype);
+                        self.src.push('(');
+                        self.src.push_str(&operands[0]);
+                        self.src.push(')');
+                    }
+                    None => self.src.push_str(&operands[0]),
+                }
             }
             Instruction::Return { .. } => {
-                self.src.push_str("(");
+                self.src.push('(');
                 self.src.push_str(&operands.join(", "));
-                self.src.push_str(")");
+                self.src.push(')');
             }
 
             Instruction::Store { .. }
@@ -628,7 +1575,7 @@ impl Bindgen for Rust<'_> {
             | Instruction::CallInterface { .. }
             | Instruction::ResultLower { .. }
             | Instruction::TupleLower { .. }
-            | Instruction::VariantPayload => unimplemented!(),
+            | Instruction::VariantPayload => self.unsupported(inst),
         }
     }
 }
@@ -636,48 +1583,177 @@ impl Bindgen for Rust<'_> {
 impl Render for InterfaceFunc {
     fn render(&self, src: &mut String) {
         rustdoc(&self.docs, src);
-        if self.name.as_str() != self.name.as_str().to_snake_case() {
+        let mut name = String::new();
+        self.name.render(&mut name);
+        let name = to_rust_ident(&name.to_snake_case()).into_owned();
+        if self.name.as_str() != name {
             src.push_str("#[link_name = \"");
             src.push_str(self.name.as_str());
             src.push_str("\"]\n");
         }
         src.push_str("pub fn ");
-        let mut name = String::new();
-        self.name.render(&mut name);
-        src.push_str(to_rust_ident(&name.to_snake_case()));
+        src.push_str(&name);
 
         let (params, results) = self.wasm_signature();
-        assert!(results.len() <= 1);
-        src.push_str("(");
+        src.push('(');
         for (i, param) in params.iter().enumerate() {
             src.push_str(&format!("arg{}: ", i));
             param.render(src);
-            src.push_str(",");
+            src.push(',');
         }
-        src.push_str(")");
+        src.push(')');
 
         if self.noreturn {
             src.push_str(" -> !");
-        } else if let Some(result) = results.get(0) {
+        } else if let Some(result) = results.first() {
             src.push_str(" -> ");
             result.render(src);
         }
-        src.push_str(";");
+        src.push(';');
+    }
+}
+
+/// Checks that `func` only takes parameters and returns results which can be
+/// rendered, lowered and lifted, returning why it can't be generated otherwise.
+fn check_supported(func: &InterfaceFunc) -> Result<(), String> {
+    let unsupported = |what: &str, name: &Id, why: String| {
+        format!(
+            "{} `{}` of function `{}` {}, which is not supported by witx-bindgen (witx {})",
+            what,
+            name.as_str(),
+            func.name.as_str(),
+            why,
+            WITX_VERSION
+        )
+    };
+    for param in func.params.iter() {
+        let what = match &param.tref {
+            TypeRef::Value(ty) => match &**ty {
+                // witx can only lower enums, including `bool`, and records,
+                // including tuples, that are named.
+                Type::Variant(v) if v.is_enum() => Some("an anonymous enum"),
+                Type::Variant(v) if v.as_expected().is_none() => {
+                    Some("an anonymous variant other than `expected`")
+                }
+                Type::Record(_) => Some("an anonymous tuple"),
+                _ => None,
+            },
+            TypeRef::Name(_) => None,
+        };
+        if let Some(what) = what {
+            return Err(unsupported(
+                "parameter",
+                &param.name,
+                format!("is {}", what),
+            ));
+        }
+        check_renderable(&param.tref)
+            .map_err(|what| unsupported("parameter", &param.name, format!("contains {}", what)))?;
+    }
+    for result in func.results.iter() {
+        check_renderable(&result.tref)
+            .map_err(|what| unsupported("result", &result.name, format!("contains {}", what)))?;
+        // The error of an `expected` is lifted from the call's return value,
+        // which can't hold a list.
+        if let Type::Variant(v) = &**result.tref.type_() {
+            if let Some((_, Some(err))) = v.as_expected() {
+                if let Type::List(_) = &**err.type_() {
+                    return Err(unsupported(
+                        "result",
+                        &result.name,
+                        "has a list as its error".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+    check_wasm_results(func)
+}
+
+/// Checks that `tref` can be rendered as a Rust type, returning what it
+/// contains that can't be otherwise.
+///
+/// Named types are rendered by name, so only anonymous ones are checked, the
+/// same way `TypeRef::render` walks them.
+fn check_renderable(tref: &TypeRef) -> Result<(), &'static str> {
+    let ty = match tref {
+        TypeRef::Name(_) => return Ok(()),
+        TypeRef::Value(ty) => ty,
+    };
+    match &**ty {
+        Type::Builtin(_) => Ok(()),
+        Type::List(t) | Type::Pointer(t) | Type::ConstPointer(t) => check_renderable(t),
+        Type::Variant(v) if v.is_bool() => Ok(()),
+        Type::Variant(v) => match v.as_expected() {
+            Some((ok, err)) => {
+                ok.map_or(Ok(()), check_renderable)?;
+                err.map_or(Ok(()), check_renderable)
+            }
+            None => Err("an anonymous variant other than `expected`"),
+        },
+        Type::Record(r) if r.is_tuple() => r
+            .members
+            .iter()
+            .try_for_each(|member| check_renderable(&member.tref)),
+        Type::Record(_) => Err("an anonymous record"),
+        Type::Handle(_) => Err("an anonymous handle"),
     }
 }
 
-fn to_rust_ident(name: &str) -> &str {
-    match name {
-        "in" => "in_",
-        "type" => "type_",
-        "yield" => "yield_",
-        s => s,
+/// Core wasm functions can only return multiple values with the multi-value
+/// proposal, which the generated imports don't use, so fail with the name of
+/// the offending function rather than emitting an invalid signature.
+fn check_wasm_results(func: &InterfaceFunc) -> Result<(), String> {
+    let results = func.wasm_signature().1.len();
+    if results > 1 {
+        return Err(format!(
+            "function `{}` returns {} core wasm values, which is not yet supported",
+            func.name.as_str(),
+            results
+        ));
     }
+    Ok(())
+}
+
+/// Strict and reserved keywords of the 2018 edition, none of which can be
+/// used as identifiers.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn to_rust_ident(name: &str) -> Cow<'_, str> {
+    if RUST_KEYWORDS.contains(&name) {
+        Cow::Owned(format!("{}_", name))
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        Cow::Owned(format!("_{}", name))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+/// Returns the Rust name of the type witx calls `name`.
+fn type_ident(name: &str) -> String {
+    to_rust_ident(&name.to_camel_case()).into_owned()
+}
+
+/// Returns the Rust name of the constant `name` of the type `ty`, which is
+/// prefixed with the type's name.
+fn const_ident(ty: &str, name: &str) -> String {
+    let ident = format!(
+        "{}_{}",
+        ty.to_shouty_snake_case(),
+        name.to_shouty_snake_case()
+    );
+    to_rust_ident(&ident).into_owned()
 }
 
 impl Render for Id {
     fn render(&self, src: &mut String) {
-        src.push_str(to_rust_ident(self.as_str()))
+        src.push_str(&to_rust_ident(self.as_str()))
     }
 }
 
@@ -692,8 +1768,25 @@ impl Render for WasmType {
     }
 }
 
-fn render_handle(src: &mut String, name: &str, _h: &HandleDatatype) {
-    src.push_str(&format!("pub type {} = u32;", name.to_camel_case()));
+fn render_handle(config: &GenerateConfig, src: &mut String, name: &str, _h: &HandleDatatype) {
+    if config.newtype_handles {
+        src.push_str("#[repr(transparent)]\n");
+        src.push_str("#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]\n");
+        src.push_str(&format!("pub struct {}(pub u32);", type_ident(name)));
+    } else {
+        src.push_str(&format!("pub type {} = u32;", type_ident(name)));
+    }
+}
+
+/// Returns the name of the handle type which `ty` is, or is an alias of.
+///
+/// As with `newtype_alias`, an alias of a handle newtype can't be used to
+/// construct it.
+fn handle_name(ty: &NamedType) -> String {
+    match &ty.tref {
+        TypeRef::Name(inner) => handle_name(inner),
+        TypeRef::Value(_) => type_ident(ty.name.as_str()),
+    }
 }
 
 fn rustdoc(docs: &str, dst: &mut String) {
@@ -703,23 +1796,23 @@ fn rustdoc(docs: &str, dst: &mut String) {
     for line in docs.lines() {
         dst.push_str("/// ");
         dst.push_str(line);
-        dst.push_str("\n");
+        dst.push('\n');
     }
 }
 
 fn rustdoc_params(docs: &[InterfaceFuncParam], header: &str, dst: &mut String) {
     let docs = docs
         .iter()
-        .filter(|param| param.docs.trim().len() > 0)
+        .filter(|param| !param.docs.trim().is_empty())
         .collect::<Vec<_>>();
-    if docs.len() == 0 {
+    if docs.is_empty() {
         return;
     }
 
     dst.push_str("///\n");
     dst.push_str("/// ## ");
     dst.push_str(header);
-    dst.push_str("\n");
+    dst.push('\n');
     dst.push_str("///\n");
 
     for param in docs {
@@ -737,7 +1830,7 @@ fn rustdoc_params(docs: &[InterfaceFuncParam], header: &str, dst: &mut String) {
                 }
             }
             dst.push_str(line);
-            dst.push_str("\n");
+            dst.push('\n');
         }
     }
 }
@@ -745,14 +1838,107 @@ fn rustdoc_params(docs: &[InterfaceFuncParam], header: &str, dst: &mut String) {
 fn record_contains_union(s: &RecordDatatype) -> bool {
     s.members
         .iter()
-        .any(|member| type_contains_union(&member.tref.type_()))
+        .any(|member| type_contains_union(member.tref.type_()))
 }
 
 fn type_contains_union(ty: &Type) -> bool {
     match ty {
         Type::Variant(c) => c.cases.iter().any(|c| c.tref.is_some()),
-        Type::List(tref) => type_contains_union(&tref.type_()),
+        Type::List(tref) => type_contains_union(tref.type_()),
         Type::Record(st) => record_contains_union(st),
         _ => false,
     }
 }
+
+/// Returns whether the Rust type for `ty` borrows linear memory, i.e. whether
+/// it's a string or list or contains one, and so needs a lifetime.
+fn type_borrows(ty: &Type) -> bool {
+    match ty {
+        Type::List(_) => true,
+        Type::Pointer(tref) | Type::ConstPointer(tref) => type_borrows(tref.type_()),
+        Type::Record(st) => record_borrows(st),
+        Type::Variant(v) => variant_borrows(v),
+        _ => false,
+    }
+}
+
+fn record_borrows(s: &RecordDatatype) -> bool {
+    s.members.iter().any(|member| match &**member.tref.type_() {
+        // List members are raw pointers, which only borrow if their elements do.
+        Type::List(elem) => type_borrows(elem.type_()),
+        ty => type_borrows(ty),
+    })
+}
+
+fn variant_borrows(v: &Variant) -> bool {
+    v.cases
+        .iter()
+        .any(|c| matches!(&c.tref, Some(t) if type_borrows(t.type_())))
+}
+
+/// Collects the Rust names of all named types that the bindings for the
+/// functions of `m` mention.
+fn referenced_types(config: &GenerateConfig, m: &Module, names: &mut BTreeSet<String>) {
+    for func in config.funcs(m) {
+        for param in func.params.iter().chain(func.results.iter()) {
+            tref_referenced_types(&param.tref, names);
+        }
+        // Results lifted straight from the call are wrapped in the newtype
+        // they resolve to, which is named differently from an alias of it.
+        for result in func.results.iter() {
+            names.extend(newtype_alias(config, &result.tref));
+            if let TypeRef::Name(nt) = &result.tref {
+                if config.newtype_handles && matches!(&**nt.type_(), Type::Handle(_)) {
+                    names.insert(handle_name(nt));
+                }
+            }
+        }
+    }
+}
+
+/// Returns whether the bindings for `func` allocate return pointers, which
+/// mirrors the `Result<T, _>` check in witx's ABI lowering.
+fn uses_return_pointer(func: &InterfaceFunc) -> bool {
+    func.results.iter().any(|r| match &**r.tref.type_() {
+        Type::Variant(v) => !v.is_enum() && v.cases[0].tref.is_some(),
+        _ => false,
+    })
+}
+
+/// Returns whether the rendering of `ty` implements `fmt` traits, which only
+/// enums do.
+fn uses_fmt(ty: &NamedType) -> bool {
+    match &ty.tref {
+        TypeRef::Value(t) => match &**t {
+            Type::Variant(v) => v.cases.iter().all(|c| c.tref.is_none()),
+            _ => false,
+        },
+        TypeRef::Name(_) => false,
+    }
+}
+
+fn tref_referenced_types(tref: &TypeRef, names: &mut BTreeSet<String>) {
+    match tref {
+        TypeRef::Name(nt) => {
+            names.insert(type_ident(nt.name.as_str()));
+        }
+        TypeRef::Value(ty) => match &**ty {
+            Type::List(t) | Type::Pointer(t) | Type::ConstPointer(t) => {
+                tref_referenced_types(t, names)
+            }
+            Type::Record(r) => {
+                for member in r.members.iter() {
+                    tref_referenced_types(&member.tref, names);
+                }
+            }
+            Type::Variant(v) => {
+                for case in v.cases.iter() {
+                    if let Some(tref) = &case.tref {
+                        tref_referenced_types(tref, names);
+                    }
+                }
+            }
+            Type::Handle(_) | Type::Builtin(_) => {}
+        },
+    }
+}
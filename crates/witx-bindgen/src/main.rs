@@ -1,10 +1,90 @@
 use std::env;
+use std::fs;
 use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "usage: witx-bindgen [--check <file> | --output <file>] [<witx>...]";
+
+/// Reports a problem with the command line and exits.
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
 
 fn main() {
-    let witx_paths = match env::args_os().nth(1) {
-        Some(path) => vec![PathBuf::from(path)],
-        None => witx::phases::snapshot().unwrap(),
+    let mut check = None;
+    let mut output = None;
+    let mut witx_paths = Vec::new();
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--check" || arg == "--output" {
+            let path = match args.next() {
+                Some(path) => PathBuf::from(path),
+                None => usage_error(&format!("`{}` requires a file path", arg.to_string_lossy())),
+            };
+            if arg == "--check" {
+                check = Some(path);
+            } else {
+                output = Some(path);
+            }
+        } else if arg.to_string_lossy().starts_with('-') {
+            usage_error(&format!("unknown option `{}`", arg.to_string_lossy()));
+        } else {
+            witx_paths.push(PathBuf::from(arg));
+        }
+    }
+    if check.is_some() && output.is_some() {
+        usage_error("`--check` and `--output` can't be used together");
+    }
+    if witx_paths.is_empty() {
+        witx_paths = witx::phases::snapshot().unwrap();
+    }
+
+    let out = match witx_bindgen::generate(&witx_paths) {
+        Ok(out) => out,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    };
+    let check = match (check, output) {
+        (Some(path), _) => path,
+        (None, Some(path)) => {
+            if let Err(e) = fs::write(&path, out) {
+                eprintln!("error: failed to write {}: {}", path.display(), e);
+                process::exit(1);
+            }
+            return;
+        }
+        (None, None) => return print!("{}", out),
+    };
+    let existing = match fs::read_to_string(&check) {
+        Ok(existing) => existing,
+        Err(e) => {
+            eprintln!("error: failed to read {}: {}", check.display(), e);
+            process::exit(1);
+        }
     };
-    print!("{}", witx_bindgen::generate(&witx_paths));
+    // Compare against the bindings generated above rather than going through
+    // `witx_bindgen::check`, which would generate them all over again.
+    if existing == out {
+        return;
+    }
+    eprintln!("error: {} is out of date", check.display());
+    let mut expected = out.lines();
+    let mut found = existing.lines();
+    for line in 1.. {
+        match (expected.next(), found.next()) {
+            (None, None) => break,
+            (e, f) if e == f => {}
+            (e, f) => {
+                eprintln!("first difference at line {}:", line);
+                eprintln!("  expected: {}", e.unwrap_or("<end of file>"));
+                eprintln!("  found:    {}", f.unwrap_or("<end of file>"));
+                break;
+            }
+        }
+    }
+    process::exit(1);
 }
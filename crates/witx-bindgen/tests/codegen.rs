@@ -0,0 +1,666 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use witx_bindgen::GenerateConfig;
+
+const EPHEMERAL: &str = "WASI/phases/ephemeral/witx";
+
+fn generate_fixture(name: &str) -> String {
+    generate_fixture_with(&GenerateConfig::default(), name)
+}
+
+fn generate_fixture_with(config: &GenerateConfig, name: &str) -> String {
+    witx_bindgen::generate_with(config, &[format!("tests/witx/{}.witx", name)]).unwrap()
+}
+
+/// Compiles `files` as sibling modules of a library crate called `name`,
+/// failing with rustc's errors if the generated code doesn't build or has
+/// any warnings.
+fn assert_compiles(name: &str, files: &[(String, String)]) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let mut lib = String::new();
+    for (file, contents) in files {
+        fs::write(dir.join(file), contents).unwrap();
+        let module = file.trim_end_matches(".rs");
+        lib.push_str(&format!(
+            "pub mod {} {{ include!(\"{}\"); }}\n",
+            module, file
+        ));
+    }
+    fs::write(dir.join("lib.rs"), lib).unwrap();
+    let output = Command::new("rustc")
+        .args([
+            "--edition",
+            "2018",
+            "--crate-type",
+            "lib",
+            "--emit",
+            "metadata",
+        ])
+        .args(["-D", "warnings", "--crate-name", name])
+        .arg("--out-dir")
+        .arg(&dir)
+        .arg(dir.join("lib.rs"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "generated code for `{}` doesn't compile:\n{}",
+        name,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn generate_files_splits_types_from_functions() {
+    let paths = ["wasi_ephemeral_clock.witx", "wasi_ephemeral_proc.witx"]
+        .iter()
+        .map(|name| format!("{}/{}", EPHEMERAL, name))
+        .collect::<Vec<_>>();
+    let files = witx_bindgen::generate_files(&paths).unwrap();
+    let names = files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            "types.rs",
+            "wasi_ephemeral_clock.rs",
+            "wasi_ephemeral_proc.rs"
+        ]
+    );
+
+    let (_, types) = &files[0];
+    assert!(types.contains("pub struct Errno("));
+    assert!(!types.contains("pub unsafe fn"));
+
+    let (_, clock) = &files[1];
+    assert!(clock.contains("use super::types::{Clockid, Errno, Timestamp};"));
+    assert!(clock.contains("pub unsafe fn res_get("));
+    assert!(!clock.contains("pub struct"));
+
+    // `proc` never uses return pointers, so it shouldn't import `MaybeUninit`.
+    let (_, proc) = &files[2];
+    assert!(!proc.contains("MaybeUninit"));
+    assert!(proc.contains("use super::types::Exitcode;"));
+}
+
+#[test]
+fn return_pointers_are_allocated_per_result() {
+    // Each result gets its own typed slot so a `u64` following a `u8` is
+    // aligned by the compiler rather than by offsetting into a shared area.
+    let src = generate_fixture("return_pointers");
+    assert!(src.contains("let mut rp0 = MaybeUninit::<Flag>::uninit();"));
+    assert!(src.contains("let mut rp1 = MaybeUninit::<Offset>::uninit();"));
+    assert!(src.contains("core::ptr::read(rp1.as_mut_ptr() as i32 as *const Offset)"));
+}
+
+#[test]
+fn char8_lists_are_bytes_and_char_lists_are_str() {
+    let src = generate_fixture("lists");
+    assert!(src.contains("pub unsafe fn put(text: &str, bytes: &[u8], raw: &[u8])"));
+    assert!(src.contains("pub type Names<'a> = &'a [&'a str];"));
+}
+
+#[test]
+fn float_newtypes_only_derive_partial_traits() {
+    let config = GenerateConfig::new().newtype_aliases(true);
+    let src = generate_fixture_with(&config, "constants");
+    assert!(src.contains(
+        "#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]\npub struct Ratio(pub f64);"
+    ));
+    assert_compiles("float_newtypes", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn generate_files_import_resolved_newtypes() {
+    let config = GenerateConfig::new()
+        .newtype_aliases(true)
+        .newtype_handles(true);
+    let files = config
+        .generate_files(&["tests/witx/resolved.witx"])
+        .unwrap();
+    let (_, resolved) = &files[1];
+    assert!(resolved.contains("use super::types::{Dirfd, Fd, Filesize, Offset};"));
+    assert_compiles("resolved_newtypes", &files);
+}
+
+#[test]
+fn builtin_aliases_are_newtypes() {
+    let src = generate_fixture_with(&GenerateConfig::new().newtype_aliases(true), "aliases");
+    assert!(src.contains("pub struct Filesize(pub u64);"));
+    assert!(src.contains("pub type Offset = Filesize;"));
+    assert!(src.contains("pub unsafe fn seek(offset: Offset) -> Filesize"));
+    assert!(src.contains("Filesize(ret as u64)"));
+    assert!(src.contains("aliases::seek(offset.0 as i64)"));
+}
+
+#[test]
+fn tuples_render_at_any_arity() {
+    let src = generate_fixture("tuples");
+    assert!(src.contains("pub unsafe fn version() -> Result<(Major, Minor, Patch), Errno>"));
+}
+
+#[test]
+fn named_tuples_are_laid_out_like_records() {
+    let src = generate_fixture("tuples");
+    assert!(src.contains("#[repr(C)]\n#[derive(Copy, Clone, Debug)]\npub struct Range {\n    pub _0: u32,\n    pub _1: u64,\n}"));
+    assert!(src.contains("pub range: Range,"));
+}
+
+#[test]
+fn tuple_lift_reads_every_member() {
+    let src = generate_fixture("tuples").replace(char::is_whitespace, "");
+    assert!(src.contains(
+        "0=>Ok((\
+         core::ptr::read(rp0.as_mut_ptr()asi32as*constMajor),\
+         core::ptr::read(rp1.as_mut_ptr()asi32as*constMinor),\
+         core::ptr::read(rp2.as_mut_ptr()asi32as*constPatch),\
+         )),"
+    ));
+}
+
+#[test]
+fn bitflags_round_trip_through_signatures() {
+    let src = generate_fixture("flags");
+    assert!(src.contains("pub unsafe fn perms_set(perms: Perms) -> Perms"));
+    assert!(src.contains("flags::perms_set(perms as i32)"));
+    assert!(src.contains("ret as Perms"));
+    assert!(src.contains("pub unsafe fn rights_set(rights: Rights) -> Rights"));
+    assert!(src.contains("flags::rights_set(rights as i64)"));
+    assert!(src.contains("ret as Rights"));
+}
+
+#[test]
+fn load_errors_are_returned() {
+    let err = witx_bindgen::generate(&["tests/witx/missing.witx"]).unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Witx(_)));
+    assert!(err.to_string().contains("tests/witx/missing.witx"));
+}
+
+#[test]
+fn paths_which_do_not_name_a_file_are_errors() {
+    for path in ["..", "/", "tests/witx/.."] {
+        let err = witx_bindgen::generate_files(&[path]).unwrap_err();
+        match &err {
+            witx_bindgen::GenerateError::Io(e) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput)
+            }
+            e => panic!("unexpected error: {}", e),
+        }
+        assert!(witx_bindgen::generate(&[path]).is_err());
+    }
+}
+
+#[test]
+fn imports_are_named_after_their_module() {
+    let src = generate_fixture("two_modules");
+    assert!(src.contains("#[link(wasm_import_module = \"first\")]"));
+    assert!(src.contains("#[link(wasm_import_module = \"second\")]"));
+    assert!(src.contains("first::ping();"));
+    assert!(src.contains("second::pong();"));
+}
+
+#[test]
+fn noreturn_wrappers_diverge() {
+    let src = generate_fixture("noreturn");
+    assert!(src.contains("pub unsafe fn halt(code: u32) -> ! {"));
+    assert!(src.contains("pub fn halt(arg0: i32) -> !;"));
+}
+
+#[test]
+fn keywords_are_escaped() {
+    let src = generate_fixture("keywords");
+    assert!(src.contains("pub move_: u32,"));
+    assert!(src.contains("pub match_: u64,"));
+    assert!(src.contains("pub unsafe fn async_(self_: Ref, dyn_: u32) -> u32"));
+    assert!(src.contains("#[link_name = \"async\"]"));
+}
+
+#[test]
+fn escaped_params_match_at_every_use() {
+    let config = GenerateConfig::new().syscall_traits(true);
+    let src = generate_fixture_with(&config, "keywords");
+    assert!(src.contains("/// * `in_` - Where to seek from."));
+    assert!(src.contains("pub unsafe fn seek(in_: u64) {\n    keywords::seek(in_ as i64);"));
+    assert!(src.contains("unsafe fn seek(&self, in_: u64) {\n        seek(in_)"));
+}
+
+#[test]
+fn flag_constants_take_the_width_of_their_repr() {
+    let src = generate_fixture("flags");
+    assert!(src.contains("pub type Perms = u8;"));
+    assert!(src.contains("pub const PERMS_WRITE: Perms = 1 << 1;"));
+    assert!(src.contains("pub type Modes = u16;"));
+    assert!(src.contains("pub const MODES_SETUID: Modes = 1 << 1;"));
+    assert!(src.contains("pub type Events = u32;"));
+    assert!(src.contains("pub const EVENTS_WRITABLE: Events = 1 << 1;"));
+    assert!(src.contains("pub type Rights = u64;"));
+    assert!(src.contains("pub const RIGHTS_TELL: Rights = 1 << 1;"));
+}
+
+#[test]
+fn generate_module_files_splits_by_module() {
+    let files = witx_bindgen::generate_module_files(&["tests/witx/two_modules.witx"]).unwrap();
+    let names = files.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["types.rs", "first.rs", "second.rs"]);
+
+    let (_, first) = &files[1];
+    assert!(first.contains("pub unsafe fn ping()"));
+    assert!(!first.contains("pong"));
+    let (_, second) = &files[2];
+    assert!(second.contains("pub unsafe fn pong()"));
+    assert!(!second.contains("ping"));
+}
+
+#[test]
+fn chars_lift_to_rust_chars() {
+    let src = generate_fixture("chars");
+    assert!(src.contains("pub unsafe fn to_upper(c: char) -> char"));
+    assert!(
+        src.contains("core::char::from_u32(ret as u32).expect(\"host returned an invalid char\")")
+    );
+    assert_compiles("chars", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn string_results_borrow_for_a_caller_chosen_lifetime() {
+    let src = generate_fixture("strings");
+    assert!(src.contains("pub unsafe fn cwd<'a>() -> Result<Path<'a>, Errno>"));
+    assert!(src.contains("let mut rp0 = MaybeUninit::<[usize; 2]>::uninit();"));
+    assert!(src.contains("let p = rp0.as_mut_ptr() as i32 as *const usize;"));
+    assert!(src.contains("core::str::from_utf8(list).expect("));
+}
+
+#[test]
+fn list_results_are_read_as_pointer_and_length() {
+    let src = generate_fixture("lists");
+    assert!(src.contains("pub unsafe fn names<'a>() -> Result<Names<'a>, Errno>"));
+    assert!(src.contains("core::slice::from_raw_parts(\n                core::ptr::read(p) as *const _,\n                core::ptr::read(p.add(1)),\n            );\n            list\n"));
+    assert_compiles("lists", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn record_strings_are_pointer_and_length_pairs() {
+    let src = generate_fixture("strings");
+    assert!(src.contains("pub struct Entry {"));
+    assert!(src.contains("pub name: *const u8,"));
+    assert!(src.contains("pub name_len: usize,"));
+    assert!(src.contains("pub path: *const u8,"));
+    assert!(src.contains("pub path_len: usize,"));
+    assert!(src.contains("pub unsafe fn lookup(entry: *const Entry) {"));
+    assert!(src.contains("which Rust doesn't guarantee.\n    pub name: &'a str,"));
+    assert!(src.contains(
+        "pub in_: *const u8,\n    /// The number of elements in `in_`.\n    pub in_len: usize,"
+    ));
+    assert_compiles("strings", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn record_length_fields_must_not_clash() {
+    let err = witx_bindgen::generate_from_str(
+        "(typename $iovec (record (field $buf (list u8)) (field $buf_len (@witx usize))))",
+    )
+    .unwrap_err();
+    match &err {
+        witx_bindgen::GenerateError::Unsupported(reason) => assert!(reason.contains(
+            "record `iovec` has a member `buf_len`, which clashes with the length field of `buf`"
+        )),
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn handles_are_newtypes() {
+    let src = generate_fixture_with(&GenerateConfig::new().newtype_handles(true), "handles");
+    assert!(src.contains("pub struct Fd(pub u32);"));
+    assert!(src.contains("pub type Dirfd = Fd;"));
+    assert!(src.contains("handles::dup(fd.0 as i32)"));
+    assert!(src.contains("Fd(ret as u32)"));
+}
+
+#[test]
+fn u64_enum_tags_are_loaded_whole() {
+    let src = generate_fixture("enums");
+    assert!(src.contains("pub struct Big(pub(crate) u64);"));
+    assert!(src.contains("0 => Ok(core::ptr::read(rp0.as_mut_ptr() as i32 as *const Big)),"));
+}
+
+#[test]
+fn enums_can_be_checked_from_raw_values() {
+    let src = generate_fixture("enums");
+    assert!(src.contains("pub const fn from_raw(raw: u16) -> Option<Errno> {\n        if raw < 2 {\n            Some(Errno(raw))\n        } else {\n            None\n        }\n    }"));
+    assert!(src.contains("pub const fn from_raw(raw: u64) -> Option<Big> {"));
+}
+
+#[test]
+fn enums_using_every_tag_value_compile() {
+    let cases = (0..256).map(|i| format!("$c{}", i)).collect::<Vec<_>>();
+    let src = witx_bindgen::generate_from_str(&format!(
+        "(typename $full (enum (@witx tag u8) {}))",
+        cases.join(" ")
+    ))
+    .unwrap();
+    assert!(src.contains(
+        "pub const fn from_raw(raw: u8) -> Option<Full> {\n        Some(Full(raw))\n    }"
+    ));
+    assert!(!src.contains("_ => \"UNKNOWN\","));
+    assert_compiles("full_enum", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn unknown_enum_values_have_a_safe_fallback() {
+    let src = generate_fixture("enums");
+    assert!(!src.contains("unreachable_unchecked"));
+    assert!(src.contains("_ => \"UNKNOWN\","));
+}
+
+#[test]
+fn multi_module_prefixes_wrappers_with_their_module() {
+    let src = generate_fixture_with(&GenerateConfig::new().multi_module(true), "two_modules");
+    assert!(src.contains("pub unsafe fn first_ping()"));
+    assert!(src.contains("pub unsafe fn second_pong()"));
+}
+
+#[test]
+fn module_namespaces_nest_wrappers_in_their_module() {
+    let config = GenerateConfig::new()
+        .module_namespaces(true)
+        .multi_module(true);
+    let src = generate_fixture_with(&config, "two_modules");
+    assert!(src.contains("pub mod first {\n    pub unsafe fn ping() {\n        raw::ping();"));
+    assert!(src.contains("pub mod raw {\n        #[link(wasm_import_module = \"first\")]"));
+    assert!(src.contains("pub mod second {"));
+}
+
+#[test]
+fn module_namespaces_only_import_types_they_use() {
+    let config = GenerateConfig::new().module_namespaces(true);
+    let src = generate_fixture_with(&config, "handles");
+    assert!(src.contains("pub mod handles {\n    use super::*;"));
+    for name in [
+        "two_modules",
+        "noreturn",
+        "constants",
+        "empty",
+        "chars",
+        "digits",
+        "handles",
+    ] {
+        let src = generate_fixture_with(&config, name);
+        assert_compiles(
+            &format!("namespaced_{}", name),
+            &[("bindings.rs".to_string(), src)],
+        );
+    }
+}
+
+#[test]
+fn syscall_traits_call_the_wrappers_by_default() {
+    let src = generate_fixture_with(&GenerateConfig::new().syscall_traits(true), "handles");
+    assert!(src.contains("pub trait HandlesSyscalls {"));
+    assert!(src.contains("    unsafe fn dup(&self, fd: Fd) -> Dirfd {\n        dup(fd)\n    }"));
+}
+
+#[test]
+fn sorted_output_is_ordered_and_reproducible() {
+    let config = GenerateConfig::new().sorted(true);
+    let src = generate_fixture_with(&config, "unsorted");
+    assert_eq!(src, generate_fixture_with(&config, "unsorted"));
+
+    let position = |item: &str| src.find(item).unwrap();
+    assert!(position("pub type Modes") < position("pub type Perms"));
+    assert!(position("pub type Perms") < position("pub type Rights"));
+    assert!(position("pub unsafe fn modes_set") < position("pub unsafe fn perms_set"));
+    assert!(position("pub unsafe fn perms_set") < position("pub unsafe fn rights_set"));
+}
+
+#[test]
+fn handle_results_are_read_from_their_return_pointer() {
+    let src = generate_fixture("handles");
+    assert!(src.contains("pub unsafe fn open(dir: Dirfd, path: &str) -> Result<Fd, Errno>"));
+    assert!(src.contains("let mut rp0 = MaybeUninit::<Fd>::uninit();"));
+    assert!(src.contains("0 => Ok(core::ptr::read(rp0.as_mut_ptr() as i32 as *const Fd)),"));
+}
+
+#[test]
+fn errors_reuse_the_call_result() {
+    let src = generate_fixture("errors");
+    assert!(src.contains("let ret = errors::sync();"));
+    assert!(src.contains("match ret {"));
+    assert!(src.contains("_ => Err(Errno(ret as u16)),"));
+}
+
+#[test]
+fn record_results_are_loaded_in_the_ok_branch() {
+    let src = generate_fixture("records");
+    assert!(src.contains("pub unsafe fn stat() -> Result<Stat, Errno>"));
+    assert!(src.contains("0 => Ok(core::ptr::read(rp0.as_mut_ptr() as i32 as *const Stat)),"));
+}
+
+#[test]
+fn nested_records_are_loaded_with_their_parent() {
+    let src = generate_fixture("records");
+    assert!(src.contains("pub times: Times,"));
+    assert!(src.contains("#[repr(C)]\n#[derive(Copy, Clone, Debug)]\npub struct Times {"));
+}
+
+#[test]
+fn record_params_are_passed_by_address() {
+    let src = generate_fixture("records");
+    assert!(src.contains("pub unsafe fn touch(times: Times) {"));
+    assert!(src.contains("records::touch(&times as *const _ as i32);"));
+}
+
+#[test]
+fn named_pointer_members_keep_their_alias() {
+    let src = generate_fixture("records");
+    assert!(src.contains("pub type Buf = *mut u8;"));
+    assert!(src.contains("pub struct Iovec {\n    pub buf: Buf,\n    pub len: u32,\n}"));
+}
+
+#[test]
+fn float_constants_get_float_literals() {
+    let src = generate_fixture("constants");
+    assert!(src.contains("pub const RATIO_ONE: Ratio = 1.0;"));
+    assert!(src.contains("pub const MASK_ALL: Mask = 18446744073709551615;"));
+}
+
+#[test]
+fn signed_constants_keep_their_sign() {
+    let src = generate_fixture("constants");
+    assert!(src.contains("pub const DELTA_MIN: Delta = -128;"));
+}
+
+#[test]
+fn hex_constants_render_integers_in_hex() {
+    let src = generate_fixture_with(&GenerateConfig::new().hex_constants(true), "constants");
+    assert!(src.contains("pub const MASK_ALL: Mask = 0xffffffffffffffff;"));
+    assert!(src.contains("pub const DELTA_MIN: Delta = -0x80;"));
+    assert!(src.contains("pub const RATIO_ONE: Ratio = 1.0;"));
+}
+
+#[test]
+fn handle_and_enum_lists_are_slices() {
+    let src = generate_fixture("lists").replace(char::is_whitespace, "");
+    assert!(src.contains("pubunsafefnwatch(fds:&[Fd],clocks:&[Clockid])"));
+    assert!(src.contains(
+        "lists::watch(\
+         fds.as_ptr()asi32,fds.len()asi32,\
+         clocks.as_ptr()asi32,clocks.len()asi32,\
+         );"
+    ));
+}
+
+#[test]
+fn generate_from_str_parses_in_memory_witx() {
+    let src = witx_bindgen::generate_from_str(
+        "(typename $size u32)
+         (module $mem
+           (@interface func (export \"grow\")
+             (param $by $size)
+             (result $old $size)))",
+    )
+    .unwrap();
+    assert!(src.contains("pub type Size = usize;"));
+    assert!(src.contains("pub unsafe fn grow(by: Size) -> Size"));
+
+    let err = witx_bindgen::generate_from_str("(typename $x").unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Witx(_)));
+}
+
+#[test]
+fn digit_leading_names_are_prefixed() {
+    let src = generate_fixture("digits");
+    assert!(src.contains("pub _2big: u32,"));
+    assert!(src.contains("pub _3d: u8,"));
+    assert!(src.contains("pub struct _2d {"));
+    assert!(src.contains("pub struct _3way(pub(crate) u8);"));
+    assert!(src.contains("pub const _3WAY_LEFT: _3way = _3way(0);"));
+    assert!(src.contains("pub type _4bits = u8;"));
+    assert!(src.contains("pub const _4BITS_LOW: _4bits = 1 << 0;"));
+    assert!(src.contains("pub const _8BIT_MAX: _8bit = 255;"));
+    assert!(
+        src.contains("pub unsafe fn turn(at: *const _2d, bits: _4bits) -> Result<_8bit, _3way>")
+    );
+    assert_compiles("digits", &[("bindings.rs".to_string(), src)]);
+}
+
+#[test]
+fn empty_modules_and_documents_generate() {
+    let src = generate_fixture("empty");
+    assert!(src.contains("pub mod empty {"));
+    assert!(!src.contains("pub unsafe fn"));
+
+    let src = witx_bindgen::generate_from_str("").unwrap();
+    assert!(src.starts_with("// This file is automatically generated, DO NOT EDIT"));
+    assert!(!src.contains("pub "));
+}
+
+#[test]
+fn custom_headers_follow_the_notice() {
+    let config = GenerateConfig::new().header("// SPDX-License-Identifier: MIT");
+    let src = generate_fixture_with(&config, "empty");
+    assert!(src.contains(
+        "// To regenerate this file run the `crates/witx-bindgen` command\n\
+         \n\
+         // SPDX-License-Identifier: MIT\n"
+    ));
+
+    let files = config.generate_files(&["tests/witx/empty.witx"]).unwrap();
+    for (_, contents) in files {
+        assert!(contents.contains("// SPDX-License-Identifier: MIT\n"));
+    }
+}
+
+#[test]
+fn payloadless_results_return_unit() {
+    let src = generate_fixture("errors");
+    assert!(src.contains("pub unsafe fn sync() -> Result<(), Errno> {"));
+    assert!(src.contains("0 => Ok(()),"));
+    assert!(!src.contains("MaybeUninit::<"));
+}
+
+#[test]
+fn only_used_imports_are_emitted() {
+    let src = generate_fixture("two_modules");
+    assert!(!src.contains("use core::"));
+    let src = generate_fixture("records");
+    assert!(src.contains("use core::fmt;\nuse core::mem::MaybeUninit;\n"));
+}
+
+#[test]
+fn filters_select_functions_and_modules() {
+    let config = GenerateConfig::new()
+        .allow_function("fd_read")
+        .allow_function("fd_write");
+    let src = witx_bindgen::generate_with(
+        &config,
+        &["WASI/phases/snapshot/witx/wasi_snapshot_preview1.witx"],
+    )
+    .unwrap();
+    assert!(src.contains("pub unsafe fn fd_read("));
+    assert!(src.contains("pub unsafe fn fd_write("));
+    assert_eq!(src.matches("pub unsafe fn ").count(), 2);
+    assert!(!src.contains("pub fn fd_close("));
+
+    let config = GenerateConfig::new().deny_module("second");
+    let src = generate_fixture_with(&config, "two_modules");
+    assert!(src.contains("pub unsafe fn ping()"));
+    assert!(!src.contains("pong"));
+}
+
+#[test]
+fn lenient_mode_skips_unsupported_functions() {
+    let src = generate_fixture_with(&GenerateConfig::new().lenient(true), "unsupported");
+    assert!(src.contains("pub unsafe fn ping() {"));
+    assert!(src.contains(
+        "// TODO: skipped because parameter `on` of function `toggle` is an anonymous enum"
+    ));
+    assert!(!src.contains("pub unsafe fn toggle("));
+    assert!(src.contains("pub fn toggle(arg0: i32);"));
+    assert!(src.contains(
+        "// - `unsupported::toggle`\n// - `unsupported::pick`\n// - `unsupported::pick_all`\n\
+         // - `unsupported::swap`\n// - `unsupported::last_error`\n"
+    ));
+    assert_compiles("lenient", &[("bindings.rs".to_string(), src)]);
+}
+
+/// Generates only `function` of the unsupported fixture, returning why it
+/// can't be.
+fn unsupported_reason(function: &str) -> String {
+    let config = GenerateConfig::new().allow_function(function);
+    match witx_bindgen::generate_with(&config, &["tests/witx/unsupported.witx"]).unwrap_err() {
+        witx_bindgen::GenerateError::Unsupported(reason) => reason,
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn anonymous_variant_parameters_are_unsupported() {
+    assert!(unsupported_reason("pick").contains(
+        "parameter `choice` of function `pick` is an anonymous variant other than `expected`"
+    ));
+    assert!(unsupported_reason("pick_all").contains(
+        "parameter `choices` of function `pick_all` contains an anonymous variant other than `expected`"
+    ));
+}
+
+#[test]
+fn anonymous_tuple_parameters_are_unsupported() {
+    assert!(unsupported_reason("swap")
+        .contains("parameter `pair` of function `swap` is an anonymous tuple"));
+}
+
+#[test]
+fn list_errors_are_unsupported() {
+    assert!(unsupported_reason("last_error")
+        .contains("result `error` of function `last_error` has a list as its error"));
+}
+
+#[test]
+fn types_containing_anonymous_variants_are_unsupported() {
+    let err = witx_bindgen::generate_from_str(
+        "(typename $choices (list (variant (case $some u32) (case $none))))",
+    )
+    .unwrap_err();
+    match &err {
+        witx_bindgen::GenerateError::Unsupported(reason) => {
+            assert!(reason
+                .contains("type `choices` contains an anonymous variant other than `expected`"))
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+}
+
+#[test]
+fn unsupported_functions_fail_by_default() {
+    let err = witx_bindgen::generate(&["tests/witx/unsupported.witx"]).unwrap_err();
+    match &err {
+        witx_bindgen::GenerateError::Unsupported(reason) => {
+            assert!(reason.contains("parameter `on` of function `toggle` is an anonymous enum"))
+        }
+        e => panic!("unexpected error: {}", e),
+    }
+    let err = witx_bindgen::generate_files(&["tests/witx/unsupported.witx"]).unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Unsupported(_)));
+}
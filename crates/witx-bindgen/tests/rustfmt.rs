@@ -0,0 +1,14 @@
+//! Kept apart from the other tests, as hiding `rustfmt` affects the whole
+//! process.
+
+#[test]
+fn missing_rustfmt_is_an_error() {
+    std::env::set_var("PATH", "");
+    let path = "tests/witx/two_modules.witx";
+    let err = witx_bindgen::generate(&[path]).unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Io(_)));
+    let err = witx_bindgen::generate_files(&[path]).unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Io(_)));
+    let err = witx_bindgen::generate_module_files(&[path]).unwrap_err();
+    assert!(matches!(err, witx_bindgen::GenerateError::Io(_)));
+}
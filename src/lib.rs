@@ -31,6 +31,8 @@
 
 #![no_std]
 
+// The generated bindings are raw imports and don't follow every lint.
+#[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
 mod lib_generated;
 pub use lib_generated::*;
 
@@ -9,7 +9,7 @@ pub type Filesize = u64;
 pub type Timestamp = u64;
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Clockid(u32);
+pub struct Clockid(pub(crate) u32);
 /// The clock measuring real time. Time value zero corresponds with
 /// 1970-01-01T00:00:00Z.
 pub const CLOCKID_REALTIME: Clockid = Clockid(0);
@@ -27,13 +27,21 @@ impl Clockid {
         self.0
     }
 
+    pub const fn from_raw(raw: u32) -> Option<Clockid> {
+        if raw < 4 {
+            Some(Clockid(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "REALTIME",
             1 => "MONOTONIC",
             2 => "PROCESS_CPUTIME_ID",
             3 => "THREAD_CPUTIME_ID",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
@@ -50,7 +58,7 @@ value of this clock therefore has no meaning."
             }
             2 => "The CPU-time clock associated with the current process.",
             3 => "The CPU-time clock associated with the current thread.",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -66,7 +74,7 @@ impl fmt::Debug for Clockid {
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Errno(u16);
+pub struct Errno(pub(crate) u16);
 /// No error occurred. System call completed successfully.
 pub const ERRNO_SUCCESS: Errno = Errno(0);
 /// Argument list too long.
@@ -226,6 +234,14 @@ impl Errno {
         self.0
     }
 
+    pub const fn from_raw(raw: u16) -> Option<Errno> {
+        if raw < 77 {
+            Some(Errno(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "SUCCESS",
@@ -305,7 +321,7 @@ impl Errno {
             74 => "TXTBSY",
             75 => "XDEV",
             76 => "NOTCAPABLE",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
@@ -387,7 +403,7 @@ impl Errno {
             74 => "Text file busy.",
             75 => "Cross-device link.",
             76 => "Extension: Capabilities insufficient.",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -507,7 +523,7 @@ pub type CiovecArray<'a> = &'a [Ciovec];
 pub type Filedelta = i64;
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Whence(u8);
+pub struct Whence(pub(crate) u8);
 /// Seek relative to start-of-file.
 pub const WHENCE_SET: Whence = Whence(0);
 /// Seek relative to current position.
@@ -519,12 +535,20 @@ impl Whence {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Whence> {
+        if raw < 3 {
+            Some(Whence(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "SET",
             1 => "CUR",
             2 => "END",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
@@ -532,7 +556,7 @@ impl Whence {
             0 => "Seek relative to start-of-file.",
             1 => "Seek relative to current position.",
             2 => "Seek relative to end-of-file.",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -551,7 +575,7 @@ pub type Dirnamlen = u32;
 pub type Inode = u64;
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Filetype(u8);
+pub struct Filetype(pub(crate) u8);
 /// The type of the file descriptor or file is unknown or is different from any of the other types specified.
 pub const FILETYPE_UNKNOWN: Filetype = Filetype(0);
 /// The file descriptor or file refers to a block device inode.
@@ -573,6 +597,14 @@ impl Filetype {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Filetype> {
+        if raw < 8 {
+            Some(Filetype(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "UNKNOWN",
@@ -583,11 +615,11 @@ impl Filetype {
             5 => "SOCKET_DGRAM",
             6 => "SOCKET_STREAM",
             7 => "SYMBOLIC_LINK",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
-        match self.0 {0 => "The type of the file descriptor or file is unknown or is different from any of the other types specified.",1 => "The file descriptor or file refers to a block device inode.",2 => "The file descriptor or file refers to a character device inode.",3 => "The file descriptor or file refers to a directory inode.",4 => "The file descriptor or file refers to a regular file inode.",5 => "The file descriptor or file refers to a datagram socket.",6 => "The file descriptor or file refers to a byte-stream socket.",7 => "The file refers to a symbolic link inode.",_ => unsafe { core::hint::unreachable_unchecked() },}
+        match self.0 {0 => "The type of the file descriptor or file is unknown or is different from any of the other types specified.",1 => "The file descriptor or file refers to a block device inode.",2 => "The file descriptor or file refers to a character device inode.",3 => "The file descriptor or file refers to a directory inode.",4 => "The file descriptor or file refers to a regular file inode.",5 => "The file descriptor or file refers to a datagram socket.",6 => "The file descriptor or file refers to a byte-stream socket.",7 => "The file refers to a symbolic link inode.",_ => "",}
     }
 }
 impl fmt::Debug for Filetype {
@@ -614,7 +646,7 @@ pub struct Dirent {
 }
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Advice(u8);
+pub struct Advice(pub(crate) u8);
 /// The application has no advice to give on its behavior with respect to the specified data.
 pub const ADVICE_NORMAL: Advice = Advice(0);
 /// The application expects to access the specified data sequentially from lower offsets to higher offsets.
@@ -632,6 +664,14 @@ impl Advice {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Advice> {
+        if raw < 6 {
+            Some(Advice(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "NORMAL",
@@ -640,11 +680,11 @@ impl Advice {
             3 => "WILLNEED",
             4 => "DONTNEED",
             5 => "NOREUSE",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
-        match self.0 {0 => "The application has no advice to give on its behavior with respect to the specified data.",1 => "The application expects to access the specified data sequentially from lower offsets to higher offsets.",2 => "The application expects to access the specified data in a random order.",3 => "The application expects to access the specified data in the near future.",4 => "The application expects that it will not access the specified data in the near future.",5 => "The application expects to access the specified data once and then not reuse it thereafter.",_ => unsafe { core::hint::unreachable_unchecked() },}
+        match self.0 {0 => "The application has no advice to give on its behavior with respect to the specified data.",1 => "The application expects to access the specified data sequentially from lower offsets to higher offsets.",2 => "The application expects to access the specified data in a random order.",3 => "The application expects to access the specified data in the near future.",4 => "The application expects that it will not access the specified data in the near future.",5 => "The application expects to access the specified data once and then not reuse it thereafter.",_ => "",}
     }
 }
 impl fmt::Debug for Advice {
@@ -733,7 +773,7 @@ pub struct Filestat {
 pub type Userdata = u64;
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Eventtype(u8);
+pub struct Eventtype(pub(crate) u8);
 /// The time value of clock `subscription_clock::id` has
 /// reached timestamp `subscription_clock::timeout`.
 pub const EVENTTYPE_CLOCK: Eventtype = Eventtype(0);
@@ -748,12 +788,20 @@ impl Eventtype {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Eventtype> {
+        if raw < 3 {
+            Some(Eventtype(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "CLOCK",
             1 => "FD_READ",
             2 => "FD_WRITE",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
@@ -770,7 +818,7 @@ available for reading. This event always triggers for regular files."
                 "File descriptor `subscription_fd_readwrite::file_descriptor` has capacity
 available for writing. This event always triggers for regular files."
             }
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -862,7 +910,7 @@ pub struct Subscription {
 pub type Exitcode = u32;
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Signal(u8);
+pub struct Signal(pub(crate) u8);
 /// No signal. Note that POSIX has special semantics for `kill(pid, 0)`,
 /// so this value is reserved.
 pub const SIGNAL_NONE: Signal = Signal(0);
@@ -961,6 +1009,14 @@ impl Signal {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Signal> {
+        if raw < 31 {
+            Some(Signal(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "NONE",
@@ -994,7 +1050,7 @@ impl Signal {
             28 => "POLL",
             29 => "PWR",
             30 => "SYS",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
@@ -1123,7 +1179,7 @@ Action: Terminates the process."
                 "Bad system call.
 Action: Terminates the process."
             }
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -1156,7 +1212,7 @@ pub const SDFLAGS_WR: Sdflags = 1 << 1;
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Preopentype(u8);
+pub struct Preopentype(pub(crate) u8);
 /// A pre-opened directory.
 pub const PREOPENTYPE_DIR: Preopentype = Preopentype(0);
 impl Preopentype {
@@ -1164,16 +1220,24 @@ impl Preopentype {
         self.0
     }
 
+    pub const fn from_raw(raw: u8) -> Option<Preopentype> {
+        if raw < 1 {
+            Some(Preopentype(raw))
+        } else {
+            None
+        }
+    }
+
     pub fn name(&self) -> &'static str {
         match self.0 {
             0 => "DIR",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "UNKNOWN",
         }
     }
     pub fn message(&self) -> &'static str {
         match self.0 {
             0 => "A pre-opened directory.",
-            _ => unsafe { core::hint::unreachable_unchecked() },
+            _ => "",
         }
     }
 }
@@ -2015,7 +2079,7 @@ pub unsafe fn poll_oneoff(
 /// ## Parameters
 ///
 /// * `rval` - The exit code returned by the process.
-pub unsafe fn proc_exit(rval: Exitcode) {
+pub unsafe fn proc_exit(rval: Exitcode) -> ! {
     wasi_snapshot_preview1::proc_exit(rval as i32);
 }
 